@@ -0,0 +1,302 @@
+//! A small self-describing container for raw textures: the pixels of [`crate::Noisetex::to_bytes`]
+//! behind a header with their size and layout, and the recipe and seed they were generated from.
+//!
+//! All header fields are little-endian, only the pixels follow [`ContainerHeader::endianness`]:
+//!
+//! | Bytes | Field |
+//! |-------|-------|
+//! | 4 | Magic, `VNTX` |
+//! | 4 | Version, currently 1 |
+//! | 3 × 4 | Width, height and depth |
+//! | 1 | Channels per pixel |
+//! | 1 | Component type: 0 unorm8, 1 unorm16, 2 float16, 3 float32 |
+//! | 1 | Pixel byte order: 0 little-endian, 1 big-endian |
+//! | 1 | Reserved, 0 |
+//! | 4 | Seed |
+//! | 4 | Texture index |
+//! | 4 | Recipe length in bytes |
+//! | n | Recipe, UTF-8 TOML |
+//! | 8 | Pixel data length in bytes |
+//! | n | Pixels, row by row and slice by slice |
+
+use std::fmt;
+use std::path::Path;
+
+use crate::export::Endianness;
+use crate::noisetex::ComponentType;
+
+pub const MAGIC: &[u8; 4] = b"VNTX";
+pub const VERSION: u32 = 1;
+
+/// Where a texture came from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Provenance {
+    /// The seed the texture was rendered with, after the recipe's per-index offset.
+    pub seed: u32,
+    pub index: u32,
+    /// The recipe as TOML, empty if unknown.
+    pub recipe: String,
+}
+
+/// Everything in a container apart from the pixels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContainerHeader {
+    pub size: glam::UVec3,
+    pub channels: usize,
+    pub component: ComponentType,
+    /// Byte order of the pixel components.
+    pub endianness: Endianness,
+    pub provenance: Provenance,
+}
+impl ContainerHeader {
+    /// Bytes of pixel data the header describes, `None` if that doesn't fit in a `u64`.
+    pub fn data_len(&self) -> Option<u64> {
+        [self.size.y, self.size.z, self.channels as u32, self.component.size() as u32]
+            .into_iter()
+            .try_fold(self.size.x as u64, |len, factor| len.checked_mul(factor as u64))
+    }
+}
+
+#[derive(Debug)]
+pub enum ContainerError {
+    Io(std::io::Error),
+    /// The data doesn't start with [`MAGIC`], so it is most likely a raw binary.
+    NotAContainer,
+    UnsupportedVersion(u32),
+    UnknownComponent(u8),
+    UnknownEndianness(u8),
+    InvalidRecipe(std::string::FromUtf8Error),
+    /// The data ends before the header or the pixels it announces.
+    Truncated,
+    /// The size and layout in the header describe more pixel data than can be addressed.
+    TooLarge,
+    /// The pixel data doesn't match the size and layout in the header.
+    SizeMismatch { expected: u64, actual: u64 },
+}
+impl fmt::Display for ContainerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContainerError::Io(err) => write!(f, "{err}"),
+            ContainerError::NotAContainer => write!(f, "not a texture container"),
+            ContainerError::UnsupportedVersion(version) => {
+                write!(f, "unsupported container version {version}")
+            }
+            ContainerError::UnknownComponent(code) => write!(f, "unknown component type {code}"),
+            ContainerError::UnknownEndianness(code) => write!(f, "unknown byte order {code}"),
+            ContainerError::InvalidRecipe(err) => write!(f, "the embedded recipe is not UTF-8: {err}"),
+            ContainerError::Truncated => write!(f, "the container is truncated"),
+            ContainerError::TooLarge => write!(f, "the header describes more pixels than fit in memory"),
+            ContainerError::SizeMismatch { expected, actual } => write!(
+                f,
+                "the header describes {expected} bytes of pixels but the container holds {actual}"
+            ),
+        }
+    }
+}
+impl std::error::Error for ContainerError {}
+
+/// Whether `bytes` start like a container.
+pub fn is_container(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Puts the header in front of `pixels`, which must already be in the header's byte order.
+pub fn encode(header: &ContainerHeader, pixels: &[u8]) -> Vec<u8> {
+    let recipe = header.provenance.recipe.as_bytes();
+
+    let mut bytes = Vec::with_capacity(48 + recipe.len() + pixels.len());
+    bytes.extend_from_slice(MAGIC);
+    for value in [VERSION, header.size.x, header.size.y, header.size.z] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes.extend_from_slice(&[
+        header.channels as u8,
+        component_code(header.component),
+        endianness_code(header.endianness),
+        0,
+    ]);
+    for value in [header.provenance.seed, header.provenance.index, recipe.len() as u32] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes.extend_from_slice(recipe);
+
+    bytes.extend_from_slice(&(pixels.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(pixels);
+
+    bytes
+}
+
+/// Splits a container into its header and pixels.
+pub fn decode(bytes: &[u8]) -> Result<(ContainerHeader, &[u8]), ContainerError> {
+    if !is_container(bytes) {
+        return Err(ContainerError::NotAContainer);
+    }
+
+    let mut reader = Reader { bytes, offset: MAGIC.len() };
+    let version = reader.u32()?;
+    if version != VERSION {
+        return Err(ContainerError::UnsupportedVersion(version));
+    }
+
+    let size = glam::uvec3(reader.u32()?, reader.u32()?, reader.u32()?);
+    let channels = reader.u8()?;
+    let component = reader.u8()?;
+    let component =
+        component_from_code(component).ok_or(ContainerError::UnknownComponent(component))?;
+    let endianness = reader.u8()?;
+    let endianness =
+        endianness_from_code(endianness).ok_or(ContainerError::UnknownEndianness(endianness))?;
+    reader.u8()?;
+
+    let seed = reader.u32()?;
+    let index = reader.u32()?;
+    let recipe_len = reader.u32()? as usize;
+    let recipe = String::from_utf8(reader.take(recipe_len)?.to_vec())
+        .map_err(ContainerError::InvalidRecipe)?;
+
+    let header = ContainerHeader {
+        size,
+        channels: channels as usize,
+        component,
+        endianness,
+        provenance: Provenance { seed, index, recipe },
+    };
+
+    let expected = header.data_len().ok_or(ContainerError::TooLarge)?;
+    let data_len = reader.u64()?;
+    if data_len != expected {
+        return Err(ContainerError::SizeMismatch {
+            expected,
+            actual: data_len,
+        });
+    }
+    let pixels = reader.take(usize::try_from(data_len).map_err(|_| ContainerError::TooLarge)?)?;
+
+    Ok((header, pixels))
+}
+
+/// Reads a container file, returning its header and pixels.
+pub fn read_file<P: AsRef<Path>>(path: P) -> Result<(ContainerHeader, Vec<u8>), ContainerError> {
+    let bytes = std::fs::read(path).map_err(ContainerError::Io)?;
+    let (header, pixels) = decode(&bytes)?;
+
+    Ok((header, pixels.to_vec()))
+}
+
+fn component_code(component: ComponentType) -> u8 {
+    match component {
+        ComponentType::Unorm8 => 0,
+        ComponentType::Unorm16 => 1,
+        ComponentType::Float16 => 2,
+        ComponentType::Float32 => 3,
+    }
+}
+
+fn component_from_code(code: u8) -> Option<ComponentType> {
+    match code {
+        0 => Some(ComponentType::Unorm8),
+        1 => Some(ComponentType::Unorm16),
+        2 => Some(ComponentType::Float16),
+        3 => Some(ComponentType::Float32),
+        _ => None,
+    }
+}
+
+fn endianness_code(endianness: Endianness) -> u8 {
+    match endianness {
+        Endianness::Little => 0,
+        Endianness::Big => 1,
+    }
+}
+
+fn endianness_from_code(code: u8) -> Option<Endianness> {
+    match code {
+        0 => Some(Endianness::Little),
+        1 => Some(Endianness::Big),
+        _ => None,
+    }
+}
+
+/// Reads little-endian fields one after another.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ContainerError> {
+        let end = self.offset.checked_add(len).ok_or(ContainerError::Truncated)?;
+        let bytes = self.bytes.get(self.offset..end).ok_or(ContainerError::Truncated)?;
+        self.offset = end;
+
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, ContainerError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, ContainerError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, ContainerError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(size: glam::UVec3) -> ContainerHeader {
+        ContainerHeader {
+            size,
+            channels: 2,
+            component: ComponentType::Float16,
+            endianness: Endianness::Big,
+            provenance: Provenance {
+                seed: 42,
+                index: 3,
+                recipe: "width = 4\n".to_owned(),
+            },
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let header = header(glam::uvec3(4, 3, 2));
+        let pixels: Vec<u8> = (0..4 * 3 * 2 * 2 * 2).map(|index| index as u8).collect();
+
+        let bytes = encode(&header, &pixels);
+        assert!(is_container(&bytes));
+
+        let (decoded, decoded_pixels) = decode(&bytes).unwrap();
+        assert_eq!(decoded, header);
+        assert_eq!(decoded_pixels, &pixels[..]);
+    }
+
+    #[test]
+    fn truncated_containers_are_rejected() {
+        let header = header(glam::uvec3(4, 3, 2));
+        let bytes = encode(&header, &[0; 4 * 3 * 2 * 2 * 2]);
+
+        // Cut off in the header, in the recipe and in the pixels.
+        for len in [6, 40, bytes.len() - 1] {
+            assert!(matches!(decode(&bytes[..len]), Err(ContainerError::Truncated)), "cut at {len}");
+        }
+    }
+
+    #[test]
+    fn oversized_headers_are_rejected() {
+        // The pixel count alone overflows a u64.
+        let bytes = encode(&header(glam::UVec3::splat(u32::MAX)), &[]);
+        assert!(matches!(decode(&bytes), Err(ContainerError::TooLarge)));
+
+        // A size that fits, but more than the container holds.
+        let bytes = encode(&header(glam::uvec3(u32::MAX, 1024, 1)), &[0; 16]);
+        assert!(matches!(
+            decode(&bytes),
+            Err(ContainerError::SizeMismatch { expected, actual: 16 }) if expected == u32::MAX as u64 * 1024 * 4
+        ));
+    }
+}
@@ -0,0 +1,386 @@
+//! DDS and KTX2 containers, so engines and tools can open textures without being told their size
+//! and format.
+
+use serde::{Deserialize, Serialize};
+
+use crate::noisetex::ComponentType;
+
+/// How the z-slices of a texture are exposed in a container.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextureLayout {
+    /// A 3D texture, or a plain 2D one for a single slice.
+    #[default]
+    Volume,
+    /// A 2D texture array with a layer per slice.
+    Array,
+}
+
+/// Byte order of the components of raw binary textures.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "snake_case")]
+pub enum Endianness {
+    #[default]
+    Little,
+    Big,
+}
+impl Endianness {
+    /// Converts little-endian components of `size` bytes to this byte order, in place.
+    pub fn reorder(&self, bytes: &mut [u8], size: usize) {
+        if *self == Endianness::Big && size > 1 {
+            for component in bytes.chunks_exact_mut(size) {
+                component.reverse();
+            }
+        }
+    }
+}
+
+/// The raw pixels of a texture along with what a container needs to describe them.
+#[derive(Debug, Clone, Copy)]
+pub struct TextureData<'a> {
+    pub size: glam::UVec3,
+    pub channels: usize,
+    pub component: ComponentType,
+    /// Pixels row by row and slice by slice, each component little-endian.
+    pub bytes: &'a [u8],
+}
+
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+const DDS_HEADER_SIZE: u32 = 124;
+const DDS_PIXEL_FORMAT_SIZE: u32 = 32;
+
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PITCH: u32 = 0x8;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+const DDSD_DEPTH: u32 = 0x80_0000;
+const DDPF_FOURCC: u32 = 0x4;
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS2_VOLUME: u32 = 0x20_0000;
+
+const D3D10_RESOURCE_DIMENSION_TEXTURE2D: u32 = 3;
+const D3D10_RESOURCE_DIMENSION_TEXTURE3D: u32 = 4;
+
+/// The DXGI format of `channels` components of `component`, with the channel count it is stored
+/// with. There are no three-channel formats apart from 32-bit float, so those get an opaque alpha.
+fn dxgi_format(channels: usize, component: ComponentType) -> (u32, usize) {
+    match (component, channels) {
+        (ComponentType::Unorm8, 1) => (61, 1),
+        (ComponentType::Unorm8, 2) => (49, 2),
+        (ComponentType::Unorm8, _) => (28, 4),
+        (ComponentType::Unorm16, 1) => (56, 1),
+        (ComponentType::Unorm16, 2) => (35, 2),
+        (ComponentType::Unorm16, _) => (11, 4),
+        (ComponentType::Float16, 1) => (54, 1),
+        (ComponentType::Float16, 2) => (34, 2),
+        (ComponentType::Float16, _) => (10, 4),
+        (ComponentType::Float32, 1) => (41, 1),
+        (ComponentType::Float32, 2) => (16, 2),
+        (ComponentType::Float32, 3) => (6, 3),
+        (ComponentType::Float32, _) => (2, 4),
+    }
+}
+
+/// Encodes a texture as a DDS file with a DX10 header, which is what allows for 3D textures and
+/// arrays in formats other than 8-bit RGBA.
+pub fn dds_bytes(texture: TextureData, layout: TextureLayout) -> Vec<u8> {
+    let TextureData { size, channels, component, bytes } = texture;
+    let (format, stored_channels) = dxgi_format(channels, component);
+    let volume = layout == TextureLayout::Volume && size.z > 1;
+    let pitch = size.x * (stored_channels * component.size()) as u32;
+
+    let mut flags = DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PITCH | DDSD_PIXELFORMAT;
+    let mut caps = DDSCAPS_TEXTURE;
+    let mut caps2 = 0;
+    if volume {
+        flags |= DDSD_DEPTH;
+        caps |= DDSCAPS_COMPLEX;
+        caps2 |= DDSCAPS2_VOLUME;
+    }
+
+    let mut header = Vec::with_capacity(148 + bytes.len());
+    header.extend_from_slice(DDS_MAGIC);
+    for value in [DDS_HEADER_SIZE, flags, size.y, size.x, pitch, if volume { size.z } else { 0 }, 1] {
+        header.extend_from_slice(&value.to_le_bytes());
+    }
+    header.extend_from_slice(&[0; 11 * 4]);
+
+    header.extend_from_slice(&DDS_PIXEL_FORMAT_SIZE.to_le_bytes());
+    header.extend_from_slice(&DDPF_FOURCC.to_le_bytes());
+    header.extend_from_slice(b"DX10");
+    header.extend_from_slice(&[0; 5 * 4]);
+
+    for value in [caps, caps2, 0, 0, 0] {
+        header.extend_from_slice(&value.to_le_bytes());
+    }
+
+    let (dimension, array_size) = if volume {
+        (D3D10_RESOURCE_DIMENSION_TEXTURE3D, 1)
+    } else {
+        (D3D10_RESOURCE_DIMENSION_TEXTURE2D, size.z.max(1))
+    };
+    for value in [format, dimension, 0, array_size, 0] {
+        header.extend_from_slice(&value.to_le_bytes());
+    }
+
+    if stored_channels == channels {
+        header.extend_from_slice(bytes);
+    } else {
+        let texel_size = channels * component.size();
+        for texel in bytes.chunks_exact(texel_size) {
+            header.extend_from_slice(texel);
+            for _ in channels..stored_channels {
+                header.extend_from_slice(component.one());
+            }
+        }
+    }
+
+    header
+}
+
+const KTX2_IDENTIFIER: [u8; 12] = [0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+/// Bytes before the data format descriptor: identifier, header, index and one level.
+const KTX2_DFD_OFFSET: usize = 12 + 9 * 4 + 4 * 4 + 2 * 8 + 3 * 8;
+
+const KHR_DF_MODEL_RGBSDA: u8 = 1;
+const KHR_DF_PRIMARIES_BT709: u8 = 1;
+const KHR_DF_TRANSFER_LINEAR: u8 = 1;
+const KHR_DF_CHANNEL_ALPHA: u8 = 15;
+const KHR_DF_SAMPLE_DATATYPE_SIGNED: u8 = 0x40;
+const KHR_DF_SAMPLE_DATATYPE_FLOAT: u8 = 0x80;
+
+/// The Vulkan format of `channels` components of `component`.
+fn vk_format(channels: usize, component: ComponentType) -> u32 {
+    let first = match component {
+        ComponentType::Unorm8 => [9, 16, 23, 37],
+        ComponentType::Unorm16 => [70, 77, 84, 91],
+        ComponentType::Float16 => [76, 83, 90, 97],
+        ComponentType::Float32 => [100, 103, 106, 109],
+    };
+
+    first[channels.clamp(1, 4) - 1]
+}
+
+/// The basic data format descriptor of a texel, one sample per channel.
+fn data_format_descriptor(channels: usize, component: ComponentType) -> Vec<u8> {
+    let component_bits = component.size() as u32 * 8;
+    let block_size = 24 + 16 * channels as u16;
+
+    let mut dfd = Vec::with_capacity(4 + block_size as usize);
+    dfd.extend_from_slice(&(4 + block_size as u32).to_le_bytes());
+    dfd.extend_from_slice(&0u32.to_le_bytes());
+    dfd.extend_from_slice(&2u16.to_le_bytes());
+    dfd.extend_from_slice(&block_size.to_le_bytes());
+    dfd.extend_from_slice(&[KHR_DF_MODEL_RGBSDA, KHR_DF_PRIMARIES_BT709, KHR_DF_TRANSFER_LINEAR, 0]);
+    dfd.extend_from_slice(&[0; 4]);
+    dfd.extend_from_slice(&[(channels * component.size()) as u8, 0, 0, 0, 0, 0, 0, 0]);
+
+    for channel in 0..channels {
+        let id = if channel == 3 { KHR_DF_CHANNEL_ALPHA } else { channel as u8 };
+        let (qualifiers, lower, upper) = match component {
+            ComponentType::Unorm8 | ComponentType::Unorm16 => (0, 0, (1u32 << component_bits) - 1),
+            ComponentType::Float16 | ComponentType::Float32 => (
+                KHR_DF_SAMPLE_DATATYPE_FLOAT | KHR_DF_SAMPLE_DATATYPE_SIGNED,
+                (-1.0f32).to_bits(),
+                1.0f32.to_bits(),
+            ),
+        };
+
+        dfd.extend_from_slice(&((channel as u32 * component_bits) as u16).to_le_bytes());
+        dfd.extend_from_slice(&[(component_bits - 1) as u8, id | qualifiers]);
+        dfd.extend_from_slice(&[0; 4]);
+        dfd.extend_from_slice(&lower.to_le_bytes());
+        dfd.extend_from_slice(&upper.to_le_bytes());
+    }
+
+    dfd
+}
+
+/// Encodes a texture as an uncompressed KTX2 file with a single mip level.
+pub fn ktx2_bytes(texture: TextureData, layout: TextureLayout) -> Vec<u8> {
+    let TextureData { size, channels, component, bytes } = texture;
+    let (depth, layers) = match layout {
+        _ if size.z <= 1 => (0, 0),
+        TextureLayout::Volume => (size.z, 0),
+        TextureLayout::Array => (0, size.z),
+    };
+
+    let dfd = data_format_descriptor(channels, component);
+    // Levels start at a multiple of both the texel size and 4.
+    let texel_size = channels * component.size();
+    let alignment = texel_size * 4 / gcd(texel_size, 4);
+    let data_offset = (KTX2_DFD_OFFSET + dfd.len()).next_multiple_of(alignment);
+
+    let mut file = Vec::with_capacity(data_offset + bytes.len());
+    file.extend_from_slice(&KTX2_IDENTIFIER);
+    let header = [
+        vk_format(channels, component),
+        component.size() as u32,
+        size.x,
+        size.y,
+        depth,
+        layers,
+        1,
+        1,
+        0,
+    ];
+    for value in header {
+        file.extend_from_slice(&value.to_le_bytes());
+    }
+
+    for value in [KTX2_DFD_OFFSET as u32, dfd.len() as u32, 0, 0] {
+        file.extend_from_slice(&value.to_le_bytes());
+    }
+    file.extend_from_slice(&[0; 2 * 8]);
+
+    for value in [data_offset, bytes.len(), bytes.len()] {
+        file.extend_from_slice(&(value as u64).to_le_bytes());
+    }
+
+    file.extend_from_slice(&dfd);
+    file.resize(data_offset, 0);
+    file.extend_from_slice(bytes);
+
+    file
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+    }
+
+    fn pixels(size: glam::UVec3, channels: usize, component: ComponentType) -> Vec<u8> {
+        let len = size.element_product() as usize * channels * component.size();
+        (0..len).map(|index| index as u8).collect()
+    }
+
+    fn texture(size: glam::UVec3, channels: usize, component: ComponentType, bytes: &[u8]) -> TextureData<'_> {
+        TextureData { size, channels, component, bytes }
+    }
+
+    #[test]
+    fn dds_2d_layout() {
+        let size = glam::uvec3(4, 2, 1);
+        let bytes = pixels(size, 2, ComponentType::Unorm8);
+        let dds = dds_bytes(texture(size, 2, ComponentType::Unorm8, &bytes), TextureLayout::Volume);
+
+        assert_eq!(dds.len(), 148 + bytes.len());
+        assert_eq!(&dds[0..4], DDS_MAGIC);
+        assert_eq!(u32_at(&dds, 4), DDS_HEADER_SIZE);
+        assert_eq!(u32_at(&dds, 8) & DDSD_DEPTH, 0);
+        assert_eq!([u32_at(&dds, 12), u32_at(&dds, 16), u32_at(&dds, 20)], [2, 4, 8]);
+        assert_eq!(u32_at(&dds, 76), DDS_PIXEL_FORMAT_SIZE);
+        assert_eq!(&dds[84..88], b"DX10");
+        assert_eq!(u32_at(&dds, 112), 0);
+        assert_eq!(u32_at(&dds, 128), 49);
+        assert_eq!(u32_at(&dds, 132), D3D10_RESOURCE_DIMENSION_TEXTURE2D);
+        assert_eq!(u32_at(&dds, 140), 1);
+        assert_eq!(&dds[148..], &bytes[..]);
+    }
+
+    #[test]
+    fn dds_volume_layout() {
+        let size = glam::uvec3(2, 2, 3);
+        let bytes = pixels(size, 3, ComponentType::Unorm8);
+        let dds = dds_bytes(texture(size, 3, ComponentType::Unorm8, &bytes), TextureLayout::Volume);
+
+        // RGB is padded to RGBA with an opaque alpha.
+        assert_eq!(dds.len(), 148 + 2 * 2 * 3 * 4);
+        assert_ne!(u32_at(&dds, 8) & DDSD_DEPTH, 0);
+        assert_eq!(u32_at(&dds, 24), 3);
+        assert_eq!(u32_at(&dds, 112), DDSCAPS2_VOLUME);
+        assert_eq!(u32_at(&dds, 128), 28);
+        assert_eq!(u32_at(&dds, 132), D3D10_RESOURCE_DIMENSION_TEXTURE3D);
+        assert_eq!(u32_at(&dds, 140), 1);
+        assert_eq!(&dds[148..156], &[0, 1, 2, 0xFF, 3, 4, 5, 0xFF]);
+    }
+
+    #[test]
+    fn dds_array_layout() {
+        let size = glam::uvec3(2, 2, 3);
+        let bytes = pixels(size, 1, ComponentType::Float32);
+        let dds = dds_bytes(texture(size, 1, ComponentType::Float32, &bytes), TextureLayout::Array);
+
+        assert_eq!(dds.len(), 148 + bytes.len());
+        assert_eq!(u32_at(&dds, 8) & DDSD_DEPTH, 0);
+        assert_eq!(u32_at(&dds, 24), 0);
+        assert_eq!(u32_at(&dds, 112), 0);
+        assert_eq!(u32_at(&dds, 128), 41);
+        assert_eq!(u32_at(&dds, 132), D3D10_RESOURCE_DIMENSION_TEXTURE2D);
+        assert_eq!(u32_at(&dds, 140), 3);
+        assert_eq!(&dds[148..], &bytes[..]);
+    }
+
+    /// Checks the index of a KTX2 file, returning where its pixels start.
+    fn check_ktx2_index(ktx2: &[u8], channels: usize, pixel_len: usize, alignment: usize) -> usize {
+        assert_eq!(&ktx2[0..12], &KTX2_IDENTIFIER);
+        assert_eq!(KTX2_DFD_OFFSET, 104);
+        assert_eq!(u32_at(ktx2, 48), KTX2_DFD_OFFSET as u32);
+        assert_eq!(u32_at(ktx2, 52), 4 + 24 + 16 * channels as u32);
+        assert_eq!(u32_at(ktx2, 104), u32_at(ktx2, 52));
+
+        let data_offset = u64_at(ktx2, 80) as usize;
+        assert!(data_offset >= KTX2_DFD_OFFSET + u32_at(ktx2, 52) as usize);
+        assert_eq!(data_offset % alignment, 0);
+        assert_eq!(u64_at(ktx2, 88), pixel_len as u64);
+        assert_eq!(u64_at(ktx2, 96), pixel_len as u64);
+        assert_eq!(ktx2.len(), data_offset + pixel_len);
+
+        data_offset
+    }
+
+    #[test]
+    fn ktx2_2d_layout() {
+        let size = glam::uvec3(4, 2, 1);
+        let bytes = pixels(size, 2, ComponentType::Unorm8);
+        let ktx2 = ktx2_bytes(texture(size, 2, ComponentType::Unorm8, &bytes), TextureLayout::Volume);
+
+        assert_eq!(u32_at(&ktx2, 12), 16);
+        assert_eq!([u32_at(&ktx2, 20), u32_at(&ktx2, 24), u32_at(&ktx2, 28), u32_at(&ktx2, 32)], [4, 2, 0, 0]);
+        let data_offset = check_ktx2_index(&ktx2, 2, bytes.len(), 4);
+        assert_eq!(data_offset, 164);
+        assert_eq!(&ktx2[data_offset..], &bytes[..]);
+    }
+
+    #[test]
+    fn ktx2_volume_layout() {
+        let size = glam::uvec3(2, 2, 3);
+        let bytes = pixels(size, 4, ComponentType::Float16);
+        let ktx2 = ktx2_bytes(texture(size, 4, ComponentType::Float16, &bytes), TextureLayout::Volume);
+
+        assert_eq!(u32_at(&ktx2, 12), 97);
+        assert_eq!(u32_at(&ktx2, 16), 2);
+        assert_eq!([u32_at(&ktx2, 28), u32_at(&ktx2, 32)], [3, 0]);
+        // The descriptor ends at 196, padded to the 8-byte texels.
+        let data_offset = check_ktx2_index(&ktx2, 4, bytes.len(), 8);
+        assert_eq!(data_offset, 200);
+        assert_eq!(&ktx2[data_offset..], &bytes[..]);
+    }
+
+    #[test]
+    fn ktx2_array_layout() {
+        let size = glam::uvec3(2, 2, 3);
+        let bytes = pixels(size, 3, ComponentType::Unorm8);
+        let ktx2 = ktx2_bytes(texture(size, 3, ComponentType::Unorm8, &bytes), TextureLayout::Array);
+
+        assert_eq!(u32_at(&ktx2, 12), 23);
+        assert_eq!([u32_at(&ktx2, 28), u32_at(&ktx2, 32)], [0, 3]);
+        // Three-byte texels align levels to 12 bytes.
+        let data_offset = check_ktx2_index(&ktx2, 3, bytes.len(), 12);
+        assert_eq!(&ktx2[data_offset..], &bytes[..]);
+    }
+}
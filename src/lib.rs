@@ -0,0 +1,18 @@
+//! Tileable 2D and 3D noise generation for volumetric clouds.
+//!
+//! Samplers live in [`samplers`] and are evaluated through [`NoiseSampler`], [`Noisetex`] fills and
+//! saves textures of any [`PixelType`], and [`Recipe`] builds both from a TOML or RON description.
+
+pub mod container;
+pub mod export;
+pub mod noisetex;
+pub mod projection;
+pub mod random;
+pub mod recipe;
+pub mod samplers;
+pub mod util;
+
+pub use noisetex::{ComponentType, Noisetex, NoisetexInfo, PixelType, SeamCheck, SeamStats};
+pub use recipe::{GeneratedTexture, Recipe, RecipeError};
+pub use samplers::{NoiseSampler, NoiseSamplerState, Smoothing};
+pub use util::Normalized;
@@ -1,321 +1,432 @@
-use core::f32;
-use std::sync::atomic::Ordering;
-
-use atomic_float::AtomicF32;
-use glam::Vec3Swizzles;
-use rayon::vec;
-
-use crate::noisetex::{NoisetexR8, NoisetexRg8, NoisetexRgb16, NoisetexRgb8, NoisetexRgba8};
-use crate::samplers::alligator::AlligatorSampler;
-use crate::samplers::fbm::{FbmSampler, FbmSamplerBuilder};
-use crate::samplers::perlin::{PerlinMode, PerlinSampler};
-use crate::samplers::vector_field::{CurlSampler, VectorFieldFbmSampler, VectorFieldSampler};
-use crate::samplers::worley::{WorleyMode, WorleySampler};
-use crate::samplers::{NoiseSampler, Smoothing};
-use crate::util::{
-    clamped_map, map, mix, remap, remap_clamp, smoothstep, CoordWrapping, MappingFn, Normalized,
-    SmoothMappingFn, SmoothingFn,
-};
-
-mod hash;
-mod noisetex;
-mod random;
-mod samplers;
-mod util;
-
-fn generate_lf_cloudmap_noisetex(index: u32, seed: &u32) {
-    let mut noisetex = NoisetexRgba8::new(512, 512, 1);
-
-    let seed = seed.wrapping_add(index);
-
-    let r_norm = Normalized::new();
-    let g_norm = Normalized::new();
-    let b_norm = Normalized::new();
-    let a_norm = Normalized::new();
-
-    noisetex.fill(|info, pixel, pos| {
-        let uvw = pos.as_vec3() / info.size().as_vec3();
-
-        pixel.r = r_norm.update_with(
-            FbmSampler::builder()
-                .sampler(PerlinSampler::builder().frequency(4.0).seed(seed).build())
-                .octaves(4)
-                .build()
-                .sample_2d(uvw.xy()),
-        );
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand};
+use half::f16;
+use volume_noise_generator::container;
+use volume_noise_generator::export::Endianness;
+use volume_noise_generator::recipe::{PixelFormat, PRESETS};
+use volume_noise_generator::{ComponentType, Recipe, SeamCheck};
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Generates tileable 2D and 3D noise textures")]
+struct Cli {
+    /// Number of worker threads, defaults to one per core.
+    #[arg(long, global = true)]
+    threads: Option<usize>,
+
+    #[command(subcommand)]
+    command: Command,
+}
 
-        pixel.g = g_norm.update_with(
-            FbmSampler::builder()
-                .sampler(
-                    WorleySampler::builder()
-                        .frequency(4.0)
-                        .seed(seed + 10)
-                        .build(),
-                )
-                .octaves(4)
-                .build()
-                .sample_2d(uvw.xy()),
-        );
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Generate textures from a recipe file or a built-in preset.
+    Generate {
+        /// Path to a .toml/.ron recipe, or the name of a built-in preset.
+        recipe: String,
+
+        #[command(flatten)]
+        options: GenerateOptions,
+
+        /// First texture index to generate.
+        #[arg(long, default_value_t = 0)]
+        start: u32,
+
+        /// Number of textures to generate, defaults to the recipe's count.
+        #[arg(long)]
+        count: Option<u32>,
+
+        /// Directory the recipe's output paths are relative to.
+        #[arg(long, default_value = "output")]
+        output_dir: PathBuf,
+
+        /// Compare opposite borders of each texture and report how visible the seams are.
+        #[arg(long)]
+        check_seams: bool,
+    },
+    /// List the built-in presets.
+    Presets,
+    /// Render a single z-slice of a recipe to a PNG.
+    Preview {
+        /// Path to a .toml/.ron recipe, or the name of a built-in preset.
+        recipe: String,
+
+        #[command(flatten)]
+        options: GenerateOptions,
+
+        /// Texture index, offsets the seed like `generate` does.
+        #[arg(long, default_value_t = 0)]
+        index: u32,
+
+        /// Z-slice to render.
+        #[arg(long, default_value_t = 0)]
+        slice: u32,
+
+        /// Frame of an animated recipe to render.
+        #[arg(long, default_value_t = 0)]
+        frame: u32,
+
+        /// Path of the PNG to write.
+        #[arg(long, default_value = "output/preview.png")]
+        output: PathBuf,
+    },
+    /// Print the layout and per-channel statistics of a container or raw .bin texture.
+    Inspect {
+        path: PathBuf,
+
+        /// Pixel format of a raw binary, containers describe their own.
+        #[arg(long)]
+        format: Option<PixelFormat>,
+
+        #[arg(long)]
+        width: Option<u32>,
+
+        #[arg(long)]
+        height: Option<u32>,
+
+        /// Byte order of the components of a raw binary.
+        #[arg(long, value_enum, default_value_t = Endianness::Little)]
+        endianness: Endianness,
+
+        /// Print the recipe a container was generated from.
+        #[arg(long)]
+        recipe: bool,
+    },
+}
 
-        pixel.b = b_norm.update_with(
-            FbmSampler::builder()
-                .sampler(
-                    PerlinSampler::builder()
-                        .frequency(44.0)
-                        .seed(seed)
-                        .build(),
-                )
-                .octaves(3)
-                .decay(2.25)
-                .build()
-                .sample_2d(uvw.xy()),
-        );
+#[derive(Debug, Args)]
+struct GenerateOptions {
+    /// Base seed, sampler seeds in the recipe are offsets from it.
+    #[arg(long, default_value_t = 0, conflicts_with = "random_seed")]
+    seed: u32,
 
-        pixel.a = a_norm.update_with(
-            FbmSampler::builder()
-                .sampler(
-                    WorleySampler::builder()
-                        .frequency(16.0)
-                        .seed(seed + 30)
-                        .build(),
-                )
-                .octaves(3)
-                .decay(2.75)
-                .build()
-                .sample_2d(uvw.xy()),
-        );
-    });
+    /// Pick a random base seed and print it.
+    #[arg(long)]
+    random_seed: bool,
 
-    noisetex.fill(|info, pixel, pos| {
-        pixel.r = pixel.r.map(r_norm.min(), r_norm.max());
-        pixel.g = pixel.g.map(g_norm.min(), g_norm.max());
-        pixel.b = pixel.b.map(b_norm.min(), b_norm.max());
-        pixel.a = pixel.a.map(a_norm.min(), a_norm.max());
-    });
+    /// Override the recipe resolution, as WIDTHxHEIGHT or WIDTHxHEIGHTxDEPTH.
+    #[arg(long, value_parser = parse_resolution)]
+    resolution: Option<glam::UVec3>,
 
-    noisetex.save_as_image(format!("output/cloudMap/cloudMapLfNoiseTex_{index}.png"));
+    /// Round frequencies that keep the textures from tiling to whole numbers, instead of only
+    /// warning about them.
+    #[arg(long)]
+    round_frequencies: bool,
 }
+impl GenerateOptions {
+    fn seed(&self) -> u32 {
+        if self.random_seed {
+            let seed = rand::random::<u32>();
+            println!("Using seed {seed}");
+            seed
+        } else {
+            self.seed
+        }
+    }
 
-fn generate_hf_cloudmap_noisetex(index: u32, seed: &u32) {
-    let mut noisetex = NoisetexRgba8::new(512, 512, 1);
-
-    let seed = seed.wrapping_add(index);
-
-    let r_norm = Normalized::new();
-    let g_norm = Normalized::new();
-    let b_norm = Normalized::new();
-    let a_norm = Normalized::new();
-
-    noisetex.fill(|info, pixel, pos| {
-        let uvw = pos.as_vec3() / (info.size().x as f32);
-
-        pixel.r = r_norm.update_with(
-            FbmSampler::builder()
-                .sampler(
-                    WorleySampler::builder()
-                        .frequency(18.0)
-                        .seed(seed + 20)
-                        .smoothing(Smoothing::Cubic)
-                        .build(),
-                )
-                .octaves(2)
-                .decay(2.75)
-                .build()
-                .sample_2d(uvw.xy()),
-        );
+    fn apply_to(&self, recipe: &mut Recipe) {
+        if let Some(resolution) = self.resolution {
+            recipe.width = resolution.x;
+            recipe.height = resolution.y;
+            recipe.depth = resolution.z;
+        }
+
+        let issues = if self.round_frequencies {
+            recipe.round_for_tiling()
+        } else {
+            recipe.tiling_issues()
+        };
+        for issue in issues.into_iter().chain(recipe.animation_issues()) {
+            eprintln!("warning: {issue}");
+        }
+    }
+}
 
-        pixel.g = g_norm.update_with({
-            let hf_alligator_noise = FbmSampler::builder()
-                .sampler(
-                    AlligatorSampler::builder()
-                        .frequency(26.0)
-                        .seed(seed + 40)
-                        .build(),
-                )
-                .octaves(3)
-                .build()
-                .sample_2d(uvw.xy())
-                .remap(0.0, 1.0, 0.2, 0.0);
-
-            1.0 - (1.0
-                - FbmSampler::builder()
-                    .sampler(
-                        AlligatorSampler::builder()
-                            .frequency(11.0)
-                            .seed(seed + 20)
-                            .build(),
-                    )
-                    .octaves(4)
-                    .build()
-                    .sample_2d(uvw.xy()))
-            .powf(1.0)
-            .max(hf_alligator_noise)
-        });
-
-        pixel.b = b_norm.update_with(0.0);
-
-        pixel.a = a_norm.update_with(0.0);
-    });
-
-    noisetex.fill(|info, pixel, pos| {
-        pixel.r = pixel.r.map(r_norm.min(), r_norm.max());
-        pixel.g = pixel.g.map(g_norm.min(), g_norm.max());
-        pixel.b = pixel.b.map(b_norm.min(), b_norm.max());
-        pixel.a = pixel.a.map(a_norm.min(), a_norm.max());
-    });
-
-    noisetex.save_as_image(format!("output/cloudMap/cloudMapHfNoiseTex_{index}.png"));
+fn parse_resolution(value: &str) -> Result<glam::UVec3, String> {
+    let parts = value
+        .split('x')
+        .map(|part| {
+            part.trim()
+                .parse::<u32>()
+                .map_err(|err| format!("invalid dimension `{part}`: {err}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match parts[..] {
+        [width, height] if width > 0 && height > 0 => Ok(glam::uvec3(width, height, 1)),
+        [width, height, depth] if width > 0 && height > 0 && depth > 0 => {
+            Ok(glam::uvec3(width, height, depth))
+        }
+        _ => Err(format!(
+            "expected WIDTHxHEIGHT or WIDTHxHEIGHTxDEPTH with non-zero dimensions, got `{value}`"
+        )),
+    }
 }
 
-fn generate_new_noise_composite_texture(index: u32, seed: &u32) {
-    let mut noisetex = NoisetexRgba8::new(128, 128, 128);
-
-    let r_norm = Normalized::new();
-    let g_norm = Normalized::new();
-    let b_norm = Normalized::new();
-    let a_norm = Normalized::new();
-
-    noisetex.fill(|info, pixel, pos| {
-        let size = glam::Vec3::splat(info.size().x as f32);
-        let uvw = pos.as_vec3() / size;
-
-        pixel.r = r_norm.update_with({
-            let curl = {
-                let vector_field_fbm_sampler = VectorFieldFbmSampler::builder()
-                    .sampler(
-                        VectorFieldSampler::builder()
-                            .frequency(3.0)
-                            .seed(*seed)
-                            .build(),
-                    )
-                    .octaves(3)
-                    .restore_original_state(true)
-                    .build();
-
-                CurlSampler::builder()
-                    .sampler(vector_field_fbm_sampler)
-                    .size(size)
-                    .build()
-                    .sample_3d(uvw)
-            };
-
-            (1.0 - FbmSampler::builder()
-                .sampler(
-                    AlligatorSampler::builder()
-                        .frequency(3.0)
-                        .seed(seed + 10)
-                        .build(),
-                )
-                .octaves(4)
-                .build()
-                .sample_3d((uvw + curl * 1.0).wrap_coord(size)))
-            .powf(1.0)
-            .quintic_smooth()
-        });
-
-        pixel.g = g_norm.update_with({
-            let curl = {
-                let vector_field_fbm_sampler = VectorFieldFbmSampler::builder()
-                    .sampler(
-                        VectorFieldSampler::builder()
-                            .frequency(5.0)
-                            .seed(seed + 20)
-                            .build(),
-                    )
-                    .octaves(3)
-                    .restore_original_state(true)
-                    .build();
-
-                CurlSampler::builder()
-                    .sampler(vector_field_fbm_sampler)
-                    .size(size)
-                    .build()
-                    .sample_3d(uvw)
-            };
-
-            (1.0 - FbmSampler::builder()
-                .sampler(
-                    AlligatorSampler::builder()
-                        .frequency(7.0)
-                        .seed(seed + 30)
-                        .build(),
-                )
-                .octaves(3)
-                .decay(2.25)
-                .build()
-                .sample_3d((uvw + curl * 1.0).wrap_coord(size)))
-            .powf(2.0)
-            .quintic_smooth()
-        });
-
-        pixel.b = b_norm.update_with({
-            (1.0 - (1.0
-                - FbmSampler::builder()
-                    .sampler(
-                        AlligatorSampler::builder()
-                            .frequency(4.0)
-                            .seed(seed + 50)
-                            .build(),
-                    )
-                    .octaves(4)
-                    .decay(2.5)
-                    .build()
-                    .sample_3d(uvw))
-            .powf(4.0))
-            .cubic_smooth()
-        });
-
-        pixel.a = a_norm.update_with({
-            (1.0 - (1.0
-                - FbmSampler::builder()
-                    .sampler(
-                        AlligatorSampler::builder()
-                            .frequency(11.0)
-                            .seed(seed + 70)
-                            .build(),
-                    )
-                    .octaves(3)
-                    .decay(2.25)
-                    .build()
-                    .sample_3d(uvw))
-            .powf(4.0))
-            .cubic_smooth()
-        });
-    });
-
-    noisetex.fill(|_info, pixel, _pos| {
-        pixel.r = pixel.r.map(r_norm.min(), r_norm.max());
-        pixel.g = pixel.g.map(g_norm.min(), g_norm.max());
-        pixel.b = pixel.b.map(b_norm.min(), b_norm.max());
-        pixel.a = pixel.a.map(a_norm.min(), a_norm.max());
-    });
-
-    // noisetex.save_as_image(format!(
-    //     "output/noiseComposite/volCloudNoiseTex_{index}.png"
-    // ));
-    noisetex.save_as_binary(format!(
-        "output/noiseComposite/volCloudNoiseTex_{index}.bin"
-    ));
+fn generate(
+    recipe_name: &str,
+    options: &GenerateOptions,
+    start: u32,
+    count: Option<u32>,
+    output_dir: &Path,
+    check_seams: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let seed = options.seed();
+
+    let mut recipe = Recipe::load_or_preset(recipe_name)?;
+    options.apply_to(&mut recipe);
+
+    let count = count.unwrap_or(recipe.count);
+    let end = start
+        .checked_add(count)
+        .ok_or_else(|| format!("--start {start} plus --count {count} is past the last texture index"))?;
+
+    for index in start..end {
+        for texture in recipe.generate_checked(output_dir, index, seed, check_seams)? {
+            for path in &texture.paths {
+                println!("Wrote {}", path.display());
+            }
+
+            if let Some(seams) = &texture.seams {
+                print_seams(seams);
+            }
+        }
+    }
+
+    Ok(())
 }
 
-fn main() {
-    let seed = 0u32;
-    // let seed = rand::random::<u32>();
+/// Borders that jump by more than this many times a typical step between neighbours count as seams.
+const SEAM_RATIO_THRESHOLD: f32 = 2.0;
+
+fn print_seams(seams: &SeamCheck) {
+    let axes = [("x", Some(seams.x)), ("y", Some(seams.y)), ("z", seams.z)];
+    for (name, stats) in axes {
+        if let Some(stats) = stats {
+            println!(
+                "  {name}: border {:.4}  interior {:.4}  ratio {:.2}",
+                stats.seam,
+                stats.interior,
+                stats.ratio()
+            );
+        }
+    }
 
-    for i in 0..1 {
-        // generate_lf_cloud_noisetex(i, &seed);
-        // generate_hf_cloud_noisetex(i, &seed);
-        generate_new_noise_composite_texture(i, &seed);
+    if seams.worst_ratio() > SEAM_RATIO_THRESHOLD {
+        eprintln!("warning: the texture has visible seams and won't tile");
     }
+}
 
-    for i in 0..10 {
-        generate_lf_cloudmap_noisetex(i, &seed);
-        generate_hf_cloudmap_noisetex(i, &seed);
+fn presets() {
+    for (name, _) in PRESETS {
+        match Recipe::preset(name) {
+            Some(Ok(recipe)) => println!("{name:<20} {}", recipe.description),
+            _ => println!("{name:<20} (failed to parse)"),
+        }
+    }
+}
+
+fn preview(
+    recipe_name: &str,
+    options: &GenerateOptions,
+    index: u32,
+    slice: u32,
+    frame: u32,
+    output: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut recipe = Recipe::load_or_preset(recipe_name)?;
+    options.apply_to(&mut recipe);
+
+    if slice >= recipe.depth {
+        return Err(format!(
+            "slice {slice} is out of range for a depth of {}",
+            recipe.depth
+        )
+        .into());
     }
 
-    for i in 0..1 {
-        // generate_3d_curl_noise(i, &seed);
+    if frame >= recipe.frame_count() {
+        return Err(format!(
+            "frame {frame} is out of range for an animation of {} frames",
+            recipe.frame_count()
+        )
+        .into());
     }
 
-    println!("Fin!");
+    let seed = recipe.seed_for_index(index, options.seed());
+    recipe.preview(output, slice, frame, seed)?;
+    println!("Wrote {}", output.display());
+
+    Ok(())
+}
+
+fn inspect(
+    path: &Path,
+    format: Option<PixelFormat>,
+    width: Option<u32>,
+    height: Option<u32>,
+    endianness: Endianness,
+    print_recipe: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let bytes = std::fs::read(path)?;
+
+    if container::is_container(&bytes) {
+        let (header, pixels) = container::decode(&bytes)?;
+        let format = PixelFormat::from_layout(header.channels, header.component).ok_or_else(|| {
+            format!(
+                "{} holds {} {:?} channels, which no pixel format matches",
+                path.display(),
+                header.channels,
+                header.component
+            )
+        })?;
+        let size = header.size;
+
+        println!(
+            "{}: {}x{}x{} {format:?} container, {:?} endian, {} bytes",
+            path.display(),
+            size.x,
+            size.y,
+            size.z,
+            header.endianness,
+            bytes.len()
+        );
+        println!("  seed {}  index {}", header.provenance.seed, header.provenance.index);
+        if print_recipe {
+            println!("{}", header.provenance.recipe);
+        }
+
+        print_channel_stats(pixels.to_vec(), format, header.endianness);
+        return Ok(());
+    }
+    if print_recipe {
+        return Err(format!("{} is a raw binary without a recipe", path.display()).into());
+    }
+
+    let (Some(format), Some(width), Some(height)) = (format, width, height) else {
+        return Err(format!(
+            "{} is a raw binary, which needs --format, --width and --height",
+            path.display()
+        )
+        .into());
+    };
+
+    let slice_size = [height as usize, format.channels(), format.bytes_per_channel()]
+        .into_iter()
+        .try_fold(width as usize, usize::checked_mul)
+        .filter(|&slice_size| slice_size != 0 && bytes.len() % slice_size == 0);
+    let Some(slice_size) = slice_size else {
+        return Err(format!(
+            "{} is {} bytes, which is not a whole number of {width}x{height} {format:?} slices",
+            path.display(),
+            bytes.len()
+        )
+        .into());
+    };
+
+    println!(
+        "{}: {width}x{height}x{} {format:?}, {} bytes",
+        path.display(),
+        bytes.len() / slice_size,
+        bytes.len()
+    );
+
+    print_channel_stats(bytes, format, endianness);
+    Ok(())
+}
+
+fn print_channel_stats(mut bytes: Vec<u8>, format: PixelFormat, endianness: Endianness) {
+    let channels = format.channels();
+    let bytes_per_channel = format.bytes_per_channel();
+    let component = format.component();
+    // Swapping back from big-endian is the same swap.
+    endianness.reorder(&mut bytes, bytes_per_channel);
+
+    let max_value = ((1u64 << (bytes_per_channel * 8)) - 1) as f32;
+    let mut min = vec![f32::MAX; channels];
+    let mut max = vec![f32::MIN; channels];
+    let mut sum = vec![0f64; channels];
+
+    for (index, value) in bytes.chunks_exact(bytes_per_channel).enumerate() {
+        let value = match component {
+            ComponentType::Float16 => f16::from_le_bytes([value[0], value[1]]).to_f32(),
+            ComponentType::Float32 => f32::from_le_bytes([value[0], value[1], value[2], value[3]]),
+            _ => {
+                value
+                    .iter()
+                    .rev()
+                    .fold(0u32, |acc, byte| (acc << 8) | *byte as u32) as f32
+                    / max_value
+            }
+        };
+        let channel = index % channels;
+
+        min[channel] = min[channel].min(value);
+        max[channel] = max[channel].max(value);
+        sum[channel] += value as f64;
+    }
+
+    let pixel_count = bytes.len() / (channels * bytes_per_channel);
+    for (channel, name) in ["r", "g", "b", "a"].iter().take(channels).enumerate() {
+        println!(
+            "  {name}: min {:.4}  max {:.4}  mean {:.4}",
+            min[channel],
+            max[channel],
+            sum[channel] / pixel_count as f64
+        );
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    if let Some(threads) = cli.threads {
+        if let Err(err) = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+        {
+            eprintln!("error: failed to set up the thread pool: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let result = match &cli.command {
+        Command::Generate {
+            recipe,
+            options,
+            start,
+            count,
+            output_dir,
+            check_seams,
+        } => generate(recipe, options, *start, *count, output_dir, *check_seams),
+        Command::Presets => {
+            presets();
+            Ok(())
+        }
+        Command::Preview {
+            recipe,
+            options,
+            index,
+            slice,
+            frame,
+            output,
+        } => preview(recipe, options, *index, *slice, *frame, output),
+        Command::Inspect {
+            path,
+            format,
+            width,
+            height,
+            endianness,
+            recipe,
+        } => inspect(path, *format, *width, *height, *endianness, *recipe),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -1,13 +1,26 @@
 use std::path::Path;
 
+use half::f16;
+
 use rayon::prelude::{IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
 
+use crate::container::{self, ContainerHeader, Provenance};
+use crate::export::{self, Endianness, TextureData, TextureLayout};
+
 pub type NoisetexRgba8 = Noisetex<Rgba8>;
 pub type NoisetexRgb8 = Noisetex<Rgb8>;
 pub type NoisetexRg8 = Noisetex<Rg8>;
 pub type NoisetexR8 = Noisetex<R8>;
 
+pub type NoisetexRgba16 = Noisetex<Rgba16>;
 pub type NoisetexRgb16 = Noisetex<Rgb16>;
+pub type NoisetexRg16 = Noisetex<Rg16>;
+pub type NoisetexR16 = Noisetex<R16>;
+
+pub type NoisetexR16f = Noisetex<R16f>;
+pub type NoisetexRg16f = Noisetex<Rg16f>;
+pub type NoisetexRgba16f = Noisetex<Rgba16f>;
+pub type NoisetexR32f = Noisetex<R32f>;
 
 #[derive(Debug, Clone)]
 pub struct NoisetexInfo {
@@ -45,6 +58,37 @@ where
         }
     }
 
+    pub fn info(&self) -> &NoisetexInfo {
+        &self.info
+    }
+
+    /// Joins textures of the same width and height into one, one after another along z.
+    pub fn stack(textures: Vec<Self>) -> Self {
+        let (width, height) = textures
+            .first()
+            .map_or((0, 0), |texture| (texture.info.width, texture.info.height));
+        let mut depth = 0;
+        let mut pixels = Vec::new();
+
+        for texture in textures {
+            assert!(
+                texture.info.width == width && texture.info.height == height,
+                "stacked textures must all have the same width and height"
+            );
+            depth += texture.info.depth;
+            pixels.extend(texture.pixels);
+        }
+
+        Self {
+            info: NoisetexInfo {
+                width,
+                height,
+                depth,
+            },
+            pixels,
+        }
+    }
+
     pub fn fill<F>(&mut self, function: F)
     where
         F: Fn(&NoisetexInfo, &mut P, glam::UVec3) + Send + Sync,
@@ -60,42 +104,249 @@ where
             });
     }
 
-    pub fn save_as_binary<Pt>(&self, path: Pt)
+    /// Saves the raw pixels, each component in `endianness` byte order.
+    pub fn save_as_binary<Pt>(&self, path: Pt, endianness: Endianness) -> std::io::Result<()>
+    where
+        Pt: AsRef<Path>,
+    {
+        let mut bytes = self.to_bytes();
+        endianness.reorder(&mut bytes, P::COMPONENT.size());
+
+        Self::write_file(path.as_ref(), &bytes)
+    }
+
+    /// Saves the raw pixels behind a header describing them, see [`container`].
+    pub fn save_as_container<Pt>(
+        &self,
+        path: Pt,
+        endianness: Endianness,
+        provenance: Provenance,
+    ) -> std::io::Result<()>
+    where
+        Pt: AsRef<Path>,
+    {
+        let mut bytes = self.to_bytes();
+        endianness.reorder(&mut bytes, P::COMPONENT.size());
+
+        let header = ContainerHeader {
+            size: self.info.size(),
+            channels: P::CHANNELS,
+            component: P::COMPONENT,
+            endianness,
+            provenance,
+        };
+        Self::write_file(path.as_ref(), &container::encode(&header, &bytes))
+    }
+
+    /// Saves the texture as a DDS file, readable by Direct3D and most texture tools.
+    pub fn save_as_dds<Pt>(&self, path: Pt, layout: TextureLayout) -> std::io::Result<()>
+    where
+        Pt: AsRef<Path>,
+    {
+        let bytes = self.to_bytes();
+        Self::write_file(path.as_ref(), &export::dds_bytes(self.texture_data(&bytes), layout))
+    }
+
+    /// Saves the texture as a KTX2 file, readable by Vulkan and most texture tools.
+    pub fn save_as_ktx2<Pt>(&self, path: Pt, layout: TextureLayout) -> std::io::Result<()>
     where
         Pt: AsRef<Path>,
     {
+        let bytes = self.to_bytes();
+        Self::write_file(path.as_ref(), &export::ktx2_bytes(self.texture_data(&bytes), layout))
+    }
+
+    /// Saves the texture as OpenEXR, half floats for half-float pixel types and full floats
+    /// otherwise. Volumes get a part per z-slice, named `slice_<z>`.
+    pub fn save_as_exr<Pt>(&self, path: Pt) -> image::ImageResult<()>
+    where
+        Pt: AsRef<Path>,
+    {
+        use exr::prelude::*;
+
         let path = path.as_ref();
+        let (width, height) = (self.info.width as usize, self.info.height as usize);
+        let names = ["R", "G", "B", "A"];
+
+        let layers: Vec<_> = self
+            .pixels
+            .chunks_exact(width * height)
+            .enumerate()
+            .map(|(slice, pixels)| {
+                let channels = (0..P::CHANNELS)
+                    .map(|index| {
+                        let values = pixels.iter().map(|pixel| pixel.channel(index));
+                        let samples = match P::COMPONENT {
+                            ComponentType::Float16 => FlatSamples::F16(values.map(f16::from_f32).collect()),
+                            _ => FlatSamples::F32(values.collect()),
+                        };
+                        AnyChannel::new(names[index], samples)
+                    })
+                    .collect();
+                let attributes = if self.info.depth > 1 {
+                    LayerAttributes::named(format!("slice_{slice}").as_str())
+                } else {
+                    LayerAttributes::default()
+                };
+
+                Layer::new((width, height), attributes, Encoding::SMALL_LOSSLESS, AnyChannels::sort(channels))
+            })
+            .collect();
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let bounds = IntegerBounds::from_dimensions((width, height));
+        Image::from_layers(ImageAttributes::new(bounds), layers)
+            .write()
+            .to_file(path)
+            .map_err(|err| {
+                image::ImageError::Encoding(image::error::EncodingError::new(
+                    image::ImageFormat::OpenExr.into(),
+                    err,
+                ))
+            })
+    }
+
+    /// The pixels as written by [`PixelType::write_to_buffer`].
+    pub fn to_bytes(&self) -> Vec<u8> {
         let mut buffer = Vec::<u8>::with_capacity(self.pixels.len() * std::mem::size_of::<P>());
 
         for pixel in self.pixels.iter() {
             pixel.write_to_buffer(&mut buffer);
         }
 
-        if !std::fs::exists(path.parent().unwrap()).unwrap() {
-            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        buffer
+    }
+
+    fn texture_data<'a>(&self, bytes: &'a [u8]) -> TextureData<'a> {
+        TextureData {
+            size: self.info.size(),
+            channels: P::CHANNELS,
+            component: P::COMPONENT,
+            bytes,
+        }
+    }
+
+    fn write_file(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
         }
 
-        std::fs::write(path, &buffer).unwrap();
+        std::fs::write(path, bytes)
     }
 
-    pub fn save_as_image<Pt>(&self, path: Pt)
+    /// Saves the texture as an image, volumes as an atlas of [`Noisetex::default_atlas_columns`].
+    pub fn save_as_image<Pt>(&self, path: Pt) -> image::ImageResult<()>
     where
         Pt: AsRef<Path>,
     {
-        let path = path.as_ref();
-        let mut img: P::ImageType = P::create_image(self.info.width, self.info.height);
+        self.save_as_atlas(path, Self::default_atlas_columns(self.info.depth))
+    }
+
+    /// Saves the z-slices side by side in a grid `columns` wide, filled row by row from the top
+    /// left.
+    pub fn save_as_atlas<Pt>(&self, path: Pt, columns: u32) -> image::ImageResult<()>
+    where
+        Pt: AsRef<Path>,
+    {
+        let (width, height) = (self.info.width, self.info.height);
+        let columns = columns.clamp(1, self.info.depth.max(1));
+        let rows = self.info.depth.div_ceil(columns).max(1);
+        let mut img: P::ImageType = P::create_image(columns * width, rows * height);
 
         for (index, pixel) in self.pixels.iter().enumerate() {
-            let (x, y, _) = Self::index_to_coord(index as u32, self.info.width, self.info.height);
+            let (x, y, z) = Self::index_to_coord(index as u32, width, height);
+
+            pixel.write_to_image(x + z % columns * width, y + z / columns * height, &mut img);
+        }
+
+        Self::save_image(path.as_ref(), img)
+    }
+
+    /// Saves z-slice `slice` on its own.
+    pub fn save_slice_as_image<Pt>(&self, path: Pt, slice: u32) -> image::ImageResult<()>
+    where
+        Pt: AsRef<Path>,
+    {
+        let (width, height) = (self.info.width, self.info.height);
+        let slice_size = (width * height) as usize;
+        let mut img: P::ImageType = P::create_image(width, height);
+
+        let pixels = self.pixels.iter().skip(slice as usize * slice_size).take(slice_size);
+        for (index, pixel) in pixels.enumerate() {
+            let (x, y, _) = Self::index_to_coord(index as u32, width, height);
 
             pixel.write_to_image(x, y, &mut img);
         }
 
-        if !std::fs::exists(path.parent().unwrap()).unwrap() {
-            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        Self::save_image(path.as_ref(), img)
+    }
+
+    /// Columns of the most square atlas of `depth` slices.
+    pub fn default_atlas_columns(depth: u32) -> u32 {
+        (depth as f32).sqrt().ceil().max(1.0) as u32
+    }
+
+    fn save_image(path: &Path, img: P::ImageType) -> image::ImageResult<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        P::save_image(path, img)
+    }
+
+    /// Compares opposite borders of the texture, to tell whether it tiles.
+    ///
+    /// The z axis is only checked for volumes.
+    pub fn seam_check(&self) -> SeamCheck {
+        SeamCheck {
+            x: self.seam_stats(glam::UVec3::X),
+            y: self.seam_stats(glam::UVec3::Y),
+            z: (self.info.depth > 1).then(|| self.seam_stats(glam::UVec3::Z)),
+        }
+    }
+
+    /// Differences between each pixel and its next neighbour along `axis`, wrapping around at the
+    /// border.
+    fn seam_stats(&self, axis: glam::UVec3) -> SeamStats {
+        let size = self.info.size();
+        let last = size.dot(axis) - 1;
+
+        let mut seam = (0.0, 0);
+        let mut interior = (0.0, 0);
+        for (index, pixel) in self.pixels.iter().enumerate() {
+            let (x, y, z) = Self::index_to_coord(index as u32, self.info.width, self.info.height);
+            let xyz = glam::uvec3(x, y, z);
+
+            let next = (xyz + axis) % size;
+            let next = &self.pixels[(next.x + (next.y + next.z * size.y) * size.x) as usize];
+            let difference = (0..P::CHANNELS)
+                .map(|channel| (pixel.channel(channel) - next.channel(channel)).abs())
+                .sum::<f32>()
+                / P::CHANNELS as f32;
+
+            let (sum, count) = if xyz.dot(axis) == last {
+                &mut seam
+            } else {
+                &mut interior
+            };
+            *sum += difference as f64;
+            *count += 1;
         }
 
-        P::save_image(path, img);
+        let mean = |(sum, count): (f64, u32)| {
+            if count == 0 {
+                0.0
+            } else {
+                (sum / count as f64) as f32
+            }
+        };
+        SeamStats {
+            seam: mean(seam),
+            interior: mean(interior),
+        }
     }
 
     fn index_to_coord(index: u32, width: u32, height: u32) -> (u32, u32, u32) {
@@ -110,13 +361,97 @@ where
     }
 }
 
+/// How well opposite borders of a texture line up, per axis.
+#[derive(Debug, Clone, Copy)]
+pub struct SeamCheck {
+    pub x: SeamStats,
+    pub y: SeamStats,
+    pub z: Option<SeamStats>,
+}
+impl SeamCheck {
+    /// The largest [`SeamStats::ratio`] over the checked axes.
+    pub fn worst_ratio(&self) -> f32 {
+        [Some(self.x), Some(self.y), self.z]
+            .into_iter()
+            .flatten()
+            .map(|stats| stats.ratio())
+            .fold(0.0, f32::max)
+    }
+}
+
+/// Mean absolute differences between neighbouring pixels along one axis, averaged over channels.
+#[derive(Debug, Clone, Copy)]
+pub struct SeamStats {
+    /// Between the last and the first pixel of each line, across the border.
+    pub seam: f32,
+    /// Between every other pair of neighbours.
+    pub interior: f32,
+}
+impl SeamStats {
+    /// How much larger the jump across the border is than a typical step, around 1 for a texture
+    /// that tiles.
+    pub fn ratio(&self) -> f32 {
+        if self.interior > 0.0 {
+            self.seam / self.interior
+        } else if self.seam > 0.0 {
+            f32::INFINITY
+        } else {
+            1.0
+        }
+    }
+}
+
+/// How each channel of a pixel is stored by [`PixelType::write_to_buffer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentType {
+    Unorm8,
+    /// Little-endian.
+    Unorm16,
+    /// Little-endian IEEE half floats.
+    Float16,
+    /// Little-endian IEEE floats.
+    Float32,
+}
+impl ComponentType {
+    /// Bytes per channel.
+    pub fn size(&self) -> usize {
+        match self {
+            ComponentType::Unorm8 => 1,
+            ComponentType::Unorm16 | ComponentType::Float16 => 2,
+            ComponentType::Float32 => 4,
+        }
+    }
+
+    /// Whether values are stored as they are, signed and unclamped.
+    pub fn is_float(&self) -> bool {
+        matches!(self, ComponentType::Float16 | ComponentType::Float32)
+    }
+
+    /// The encoding of 1.0, for padding out channels.
+    pub fn one(&self) -> &'static [u8] {
+        match self {
+            ComponentType::Unorm8 => &[0xFF],
+            ComponentType::Unorm16 => &[0xFF, 0xFF],
+            ComponentType::Float16 => &[0x00, 0x3C],
+            ComponentType::Float32 => &[0x00, 0x00, 0x80, 0x3F],
+        }
+    }
+}
+
 pub trait PixelType: Sized + Send + Sync + Clone + Default {
     type ImageType: image::GenericImage + image::GenericImageView;
     type ImagePixelType: image::Pixel;
 
+    const CHANNELS: usize;
+    const COMPONENT: ComponentType;
+
+    fn channel(&self, index: usize) -> f32;
+
+    fn set_channel(&mut self, index: usize, value: f32);
+
     fn create_image(width: u32, height: u32) -> Self::ImageType;
 
-    fn save_image<P>(path: P, img: Self::ImageType)
+    fn save_image<P>(path: P, img: Self::ImageType) -> image::ImageResult<()>
     where
         P: AsRef<Path>;
 
@@ -177,15 +512,38 @@ impl PixelType for Rgba8 {
     type ImageType = image::RgbaImage;
     type ImagePixelType = image::Rgba<u8>;
 
+    const CHANNELS: usize = 4;
+    const COMPONENT: ComponentType = ComponentType::Unorm8;
+
+    fn channel(&self, index: usize) -> f32 {
+        match index {
+            0 => self.r,
+            1 => self.g,
+            2 => self.b,
+            3 => self.a,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
+    fn set_channel(&mut self, index: usize, value: f32) {
+        match index {
+            0 => self.r = value,
+            1 => self.g = value,
+            2 => self.b = value,
+            3 => self.a = value,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
     fn create_image(width: u32, height: u32) -> Self::ImageType {
         image::RgbaImage::new(width, height)
     }
 
-    fn save_image<P>(path: P, img: Self::ImageType)
+    fn save_image<P>(path: P, img: Self::ImageType) -> image::ImageResult<()>
     where
         P: AsRef<Path>,
     {
-        img.save(path).unwrap();
+        img.save(path)
     }
 
     fn write_to_image(&self, x: u32, y: u32, img: &mut Self::ImageType) {
@@ -238,15 +596,36 @@ impl PixelType for Rgb8 {
     type ImageType = image::RgbImage;
     type ImagePixelType = image::Rgb<u8>;
 
+    const CHANNELS: usize = 3;
+    const COMPONENT: ComponentType = ComponentType::Unorm8;
+
+    fn channel(&self, index: usize) -> f32 {
+        match index {
+            0 => self.r,
+            1 => self.g,
+            2 => self.b,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
+    fn set_channel(&mut self, index: usize, value: f32) {
+        match index {
+            0 => self.r = value,
+            1 => self.g = value,
+            2 => self.b = value,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
     fn create_image(width: u32, height: u32) -> Self::ImageType {
         image::RgbImage::new(width, height)
     }
 
-    fn save_image<P>(path: P, img: Self::ImageType)
+    fn save_image<P>(path: P, img: Self::ImageType) -> image::ImageResult<()>
     where
         P: AsRef<Path>,
     {
-        img.save(path).unwrap();
+        img.save(path)
     }
 
     fn write_to_image(&self, x: u32, y: u32, img: &mut Self::ImageType) {
@@ -286,18 +665,39 @@ impl From<f32> for Rgb16 {
     }
 }
 impl PixelType for Rgb16 {
-    type ImageType = image::RgbImage;
-    type ImagePixelType = image::Rgb<u8>;
+    type ImageType = image::ImageBuffer<image::Rgb<u16>, Vec<u16>>;
+    type ImagePixelType = image::Rgb<u16>;
+
+    const CHANNELS: usize = 3;
+    const COMPONENT: ComponentType = ComponentType::Unorm16;
+
+    fn channel(&self, index: usize) -> f32 {
+        match index {
+            0 => self.r,
+            1 => self.g,
+            2 => self.b,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
+    fn set_channel(&mut self, index: usize, value: f32) {
+        match index {
+            0 => self.r = value,
+            1 => self.g = value,
+            2 => self.b = value,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
 
     fn create_image(width: u32, height: u32) -> Self::ImageType {
-        image::RgbImage::new(width, height)
+        image::ImageBuffer::new(width, height)
     }
 
-    fn save_image<P>(path: P, img: Self::ImageType)
+    fn save_image<P>(path: P, img: Self::ImageType) -> image::ImageResult<()>
     where
         P: AsRef<Path>,
     {
-        img.save(path).unwrap();
+        img.save(path)
     }
 
     fn write_to_image(&self, x: u32, y: u32, img: &mut Self::ImageType) {
@@ -305,16 +705,214 @@ impl PixelType for Rgb16 {
     }
 
     fn write_to_buffer(&self, buffer: &mut Vec<u8>) {
-        let r_as_u16: u16 = self.r.to_color();
-        let g_as_u16: u16 = self.g.to_color();
-        let b_as_u16: u16 = self.b.to_color();
+        for value in [self.r, self.g, self.b] {
+            buffer.extend_from_slice(&ToColor::<u16>::to_color(value).to_le_bytes());
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone)]
+pub struct Rgba16 {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+impl Default for Rgba16 {
+    fn default() -> Self {
+        Self {
+            r: 0.0,
+            g: 0.0,
+            b: 0.0,
+            a: 1.0,
+        }
+    }
+}
+impl From<glam::Vec4> for Rgba16 {
+    fn from(value: glam::Vec4) -> Self {
+        Self {
+            r: value.x,
+            g: value.y,
+            b: value.z,
+            a: value.w,
+        }
+    }
+}
+impl From<f32> for Rgba16 {
+    fn from(value: f32) -> Self {
+        Self {
+            r: value,
+            g: value,
+            b: value,
+            a: 1.0,
+        }
+    }
+}
+impl PixelType for Rgba16 {
+    type ImageType = image::ImageBuffer<image::Rgba<u16>, Vec<u16>>;
+    type ImagePixelType = image::Rgba<u16>;
+
+    const CHANNELS: usize = 4;
+    const COMPONENT: ComponentType = ComponentType::Unorm16;
+
+    fn channel(&self, index: usize) -> f32 {
+        match index {
+            0 => self.r,
+            1 => self.g,
+            2 => self.b,
+            3 => self.a,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
+    fn set_channel(&mut self, index: usize, value: f32) {
+        match index {
+            0 => self.r = value,
+            1 => self.g = value,
+            2 => self.b = value,
+            3 => self.a = value,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
+    fn create_image(width: u32, height: u32) -> Self::ImageType {
+        image::ImageBuffer::new(width, height)
+    }
+
+    fn save_image<P>(path: P, img: Self::ImageType) -> image::ImageResult<()>
+    where
+        P: AsRef<Path>,
+    {
+        img.save(path)
+    }
+
+    fn write_to_image(&self, x: u32, y: u32, img: &mut Self::ImageType) {
+        img.put_pixel(x, y, image::Rgba([self.r.to_color(), self.g.to_color(), self.b.to_color(), self.a.to_color()]));
+    }
+
+    fn write_to_buffer(&self, buffer: &mut Vec<u8>) {
+        for value in [self.r, self.g, self.b, self.a] {
+            buffer.extend_from_slice(&ToColor::<u16>::to_color(value).to_le_bytes());
+        }
+    }
+}
 
-        buffer.push(r_as_u16 as u8);
-        buffer.push((r_as_u16 >> 8) as u8);
-        buffer.push(g_as_u16 as u8);
-        buffer.push((g_as_u16 >> 8) as u8);
-        buffer.push(b_as_u16 as u8);
-        buffer.push((b_as_u16 >> 8) as u8);
+#[repr(C)]
+#[derive(Debug, Clone, Default)]
+pub struct Rg16 {
+    pub r: f32,
+    pub g: f32,
+}
+impl From<f32> for Rg16 {
+    fn from(value: f32) -> Self {
+        Self {
+            r: value,
+            g: value,
+        }
+    }
+}
+impl From<glam::Vec2> for Rg16 {
+    fn from(value: glam::Vec2) -> Self {
+        Self {
+            r: value.x,
+            g: value.y,
+        }
+    }
+}
+impl PixelType for Rg16 {
+    type ImageType = image::ImageBuffer<image::LumaA<u16>, Vec<u16>>;
+    type ImagePixelType = image::LumaA<u16>;
+
+    const CHANNELS: usize = 2;
+    const COMPONENT: ComponentType = ComponentType::Unorm16;
+
+    fn channel(&self, index: usize) -> f32 {
+        match index {
+            0 => self.r,
+            1 => self.g,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
+    fn set_channel(&mut self, index: usize, value: f32) {
+        match index {
+            0 => self.r = value,
+            1 => self.g = value,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
+    fn create_image(width: u32, height: u32) -> Self::ImageType {
+        image::ImageBuffer::new(width, height)
+    }
+
+    fn save_image<P>(path: P, img: Self::ImageType) -> image::ImageResult<()>
+    where
+        P: AsRef<Path>,
+    {
+        img.save(path)
+    }
+
+    fn write_to_image(&self, x: u32, y: u32, img: &mut Self::ImageType) {
+        img.put_pixel(x, y, image::LumaA([self.r.to_color(), self.g.to_color()]));
+    }
+
+    fn write_to_buffer(&self, buffer: &mut Vec<u8>) {
+        for value in [self.r, self.g] {
+            buffer.extend_from_slice(&ToColor::<u16>::to_color(value).to_le_bytes());
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Default)]
+pub struct R16 {
+    pub r: f32,
+}
+impl From<f32> for R16 {
+    fn from(value: f32) -> Self {
+        Self { r: value }
+    }
+}
+impl PixelType for R16 {
+    type ImageType = image::ImageBuffer<image::Luma<u16>, Vec<u16>>;
+    type ImagePixelType = image::Luma<u16>;
+
+    const CHANNELS: usize = 1;
+    const COMPONENT: ComponentType = ComponentType::Unorm16;
+
+    fn channel(&self, index: usize) -> f32 {
+        match index {
+            0 => self.r,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
+    fn set_channel(&mut self, index: usize, value: f32) {
+        match index {
+            0 => self.r = value,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
+    fn create_image(width: u32, height: u32) -> Self::ImageType {
+        image::ImageBuffer::new(width, height)
+    }
+
+    fn save_image<P>(path: P, img: Self::ImageType) -> image::ImageResult<()>
+    where
+        P: AsRef<Path>,
+    {
+        img.save(path)
+    }
+
+    fn write_to_image(&self, x: u32, y: u32, img: &mut Self::ImageType) {
+        img.put_pixel(x, y, image::Luma([self.r.to_color()]));
+    }
+
+    fn write_to_buffer(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&ToColor::<u16>::to_color(self.r).to_le_bytes());
     }
 }
 
@@ -344,15 +942,34 @@ impl PixelType for Rg8 {
     type ImageType = image::GrayAlphaImage;
     type ImagePixelType = image::LumaA<u8>;
 
+    const CHANNELS: usize = 2;
+    const COMPONENT: ComponentType = ComponentType::Unorm8;
+
+    fn channel(&self, index: usize) -> f32 {
+        match index {
+            0 => self.r,
+            1 => self.g,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
+    fn set_channel(&mut self, index: usize, value: f32) {
+        match index {
+            0 => self.r = value,
+            1 => self.g = value,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
     fn create_image(width: u32, height: u32) -> Self::ImageType {
         image::GrayAlphaImage::new(width, height)
     }
 
-    fn save_image<P>(path: P, img: Self::ImageType)
+    fn save_image<P>(path: P, img: Self::ImageType) -> image::ImageResult<()>
     where
         P: AsRef<Path>,
     {
-        img.save(path).unwrap();
+        img.save(path)
     }
 
     fn write_to_image(&self, x: u32, y: u32, img: &mut Self::ImageType) {
@@ -368,36 +985,320 @@ impl PixelType for Rg8 {
 #[repr(C)]
 #[derive(Debug, Clone, Default)]
 pub struct R8 {
-    pub r: u8,
+    pub r: f32,
 }
 impl From<f32> for R8 {
     fn from(value: f32) -> Self {
-        Self {
-            r: (value * 255f32) as u8,
-        }
+        Self { r: value }
     }
 }
 impl PixelType for R8 {
     type ImageType = image::GrayImage;
     type ImagePixelType = image::Luma<u8>;
 
+    const CHANNELS: usize = 1;
+    const COMPONENT: ComponentType = ComponentType::Unorm8;
+
+    fn channel(&self, index: usize) -> f32 {
+        match index {
+            0 => self.r,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
+    fn set_channel(&mut self, index: usize, value: f32) {
+        match index {
+            0 => self.r = value,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
     fn create_image(width: u32, height: u32) -> Self::ImageType {
         image::GrayImage::new(width, height)
     }
 
-    fn save_image<P>(path: P, img: Self::ImageType)
+    fn save_image<P>(path: P, img: Self::ImageType) -> image::ImageResult<()>
+    where
+        P: AsRef<Path>,
+    {
+        img.save(path)
+    }
+
+    fn write_to_image(&self, x: u32, y: u32, img: &mut Self::ImageType) {
+        img.put_pixel(x, y, image::Luma([self.r.to_color()]));
+    }
+
+    fn write_to_buffer(&self, buffer: &mut Vec<u8>) {
+        buffer.push(self.r.to_color());
+    }
+}
+
+/// Saves a float image as OpenEXR if the path asks for it, otherwise as 16 bits per channel since
+/// other formats have no floats.
+fn save_float_image<P>(path: P, img: image::DynamicImage) -> image::ImageResult<()>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let is_exr = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("exr"));
+
+    if is_exr {
+        img.save(path)
+    } else if img.color().has_alpha() {
+        img.to_rgba16().save(path)
+    } else {
+        img.to_rgb16().save(path)
+    }
+}
+
+/// A single channel stored as a half float, unclamped.
+#[repr(C)]
+#[derive(Debug, Clone, Default)]
+pub struct R16f {
+    pub r: f32,
+}
+impl From<f32> for R16f {
+    fn from(value: f32) -> Self {
+        Self { r: value }
+    }
+}
+impl PixelType for R16f {
+    type ImageType = image::Rgb32FImage;
+    type ImagePixelType = image::Rgb<f32>;
+
+    const CHANNELS: usize = 1;
+    const COMPONENT: ComponentType = ComponentType::Float16;
+
+    fn channel(&self, index: usize) -> f32 {
+        match index {
+            0 => self.r,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
+    fn set_channel(&mut self, index: usize, value: f32) {
+        match index {
+            0 => self.r = value,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
+    fn create_image(width: u32, height: u32) -> Self::ImageType {
+        image::Rgb32FImage::new(width, height)
+    }
+
+    fn save_image<P>(path: P, img: Self::ImageType) -> image::ImageResult<()>
+    where
+        P: AsRef<Path>,
+    {
+        save_float_image(path, img.into())
+    }
+
+    fn write_to_image(&self, x: u32, y: u32, img: &mut Self::ImageType) {
+        img.put_pixel(x, y, image::Rgb([self.r, self.r, self.r]));
+    }
+
+    fn write_to_buffer(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&f16::from_f32(self.r).to_le_bytes());
+    }
+}
+
+/// Two channels stored as half floats, unclamped.
+#[repr(C)]
+#[derive(Debug, Clone, Default)]
+pub struct Rg16f {
+    pub r: f32,
+    pub g: f32,
+}
+impl From<f32> for Rg16f {
+    fn from(value: f32) -> Self {
+        Self {
+            r: value,
+            g: value,
+        }
+    }
+}
+impl From<glam::Vec2> for Rg16f {
+    fn from(value: glam::Vec2) -> Self {
+        Self {
+            r: value.x,
+            g: value.y,
+        }
+    }
+}
+impl PixelType for Rg16f {
+    type ImageType = image::Rgb32FImage;
+    type ImagePixelType = image::Rgb<f32>;
+
+    const CHANNELS: usize = 2;
+    const COMPONENT: ComponentType = ComponentType::Float16;
+
+    fn channel(&self, index: usize) -> f32 {
+        match index {
+            0 => self.r,
+            1 => self.g,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
+    fn set_channel(&mut self, index: usize, value: f32) {
+        match index {
+            0 => self.r = value,
+            1 => self.g = value,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
+    fn create_image(width: u32, height: u32) -> Self::ImageType {
+        image::Rgb32FImage::new(width, height)
+    }
+
+    fn save_image<P>(path: P, img: Self::ImageType) -> image::ImageResult<()>
+    where
+        P: AsRef<Path>,
+    {
+        save_float_image(path, img.into())
+    }
+
+    fn write_to_image(&self, x: u32, y: u32, img: &mut Self::ImageType) {
+        img.put_pixel(x, y, image::Rgb([self.r, self.g, 0.0]));
+    }
+
+    fn write_to_buffer(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&f16::from_f32(self.r).to_le_bytes());
+        buffer.extend_from_slice(&f16::from_f32(self.g).to_le_bytes());
+    }
+}
+
+/// Four channels stored as half floats, unclamped.
+#[repr(C)]
+#[derive(Debug, Clone)]
+pub struct Rgba16f {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+impl Default for Rgba16f {
+    fn default() -> Self {
+        Self {
+            r: 0.0,
+            g: 0.0,
+            b: 0.0,
+            a: 1.0,
+        }
+    }
+}
+impl From<glam::Vec4> for Rgba16f {
+    fn from(value: glam::Vec4) -> Self {
+        Self {
+            r: value.x,
+            g: value.y,
+            b: value.z,
+            a: value.w,
+        }
+    }
+}
+impl PixelType for Rgba16f {
+    type ImageType = image::Rgba32FImage;
+    type ImagePixelType = image::Rgba<f32>;
+
+    const CHANNELS: usize = 4;
+    const COMPONENT: ComponentType = ComponentType::Float16;
+
+    fn channel(&self, index: usize) -> f32 {
+        match index {
+            0 => self.r,
+            1 => self.g,
+            2 => self.b,
+            3 => self.a,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
+    fn set_channel(&mut self, index: usize, value: f32) {
+        match index {
+            0 => self.r = value,
+            1 => self.g = value,
+            2 => self.b = value,
+            3 => self.a = value,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
+    fn create_image(width: u32, height: u32) -> Self::ImageType {
+        image::Rgba32FImage::new(width, height)
+    }
+
+    fn save_image<P>(path: P, img: Self::ImageType) -> image::ImageResult<()>
+    where
+        P: AsRef<Path>,
+    {
+        save_float_image(path, img.into())
+    }
+
+    fn write_to_image(&self, x: u32, y: u32, img: &mut Self::ImageType) {
+        img.put_pixel(x, y, image::Rgba([self.r, self.g, self.b, self.a]));
+    }
+
+    fn write_to_buffer(&self, buffer: &mut Vec<u8>) {
+        for value in [self.r, self.g, self.b, self.a] {
+            buffer.extend_from_slice(&f16::from_f32(value).to_le_bytes());
+        }
+    }
+}
+
+/// A single channel stored as a full float, unclamped.
+#[repr(C)]
+#[derive(Debug, Clone, Default)]
+pub struct R32f {
+    pub r: f32,
+}
+impl From<f32> for R32f {
+    fn from(value: f32) -> Self {
+        Self { r: value }
+    }
+}
+impl PixelType for R32f {
+    type ImageType = image::Rgb32FImage;
+    type ImagePixelType = image::Rgb<f32>;
+
+    const CHANNELS: usize = 1;
+    const COMPONENT: ComponentType = ComponentType::Float32;
+
+    fn channel(&self, index: usize) -> f32 {
+        match index {
+            0 => self.r,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
+    fn set_channel(&mut self, index: usize, value: f32) {
+        match index {
+            0 => self.r = value,
+            _ => panic!("Channel index {index} is out of range"),
+        }
+    }
+
+    fn create_image(width: u32, height: u32) -> Self::ImageType {
+        image::Rgb32FImage::new(width, height)
+    }
+
+    fn save_image<P>(path: P, img: Self::ImageType) -> image::ImageResult<()>
     where
         P: AsRef<Path>,
     {
-        img.save(path).unwrap();
+        save_float_image(path, img.into())
     }
 
     fn write_to_image(&self, x: u32, y: u32, img: &mut Self::ImageType) {
-        img.put_pixel(x, y, image::Luma([self.r]));
+        img.put_pixel(x, y, image::Rgb([self.r, self.r, self.r]));
     }
 
     fn write_to_buffer(&self, buffer: &mut Vec<u8>) {
-        buffer.push(self.r);
+        buffer.extend_from_slice(&self.r.to_le_bytes());
     }
 }
 
@@ -412,6 +1313,152 @@ impl ToColor<u8> for f32 {
 }
 impl ToColor<u16> for f32 {
     fn to_color(self) -> u16 {
-        (self * (u8::MAX as f32)) as u16
+        (self * (u16::MAX as f32)) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samplers::blue_noise::BlueNoiseSampler;
+    use crate::samplers::fbm::{FbmMode, FbmSampler};
+    use crate::samplers::perlin::PerlinSampler;
+    use crate::samplers::NoiseSampler;
+
+    fn perlin_texture(frequency: f32) -> NoisetexR32f {
+        let sampler = PerlinSampler::builder().frequency(frequency).seed(7).build();
+
+        let mut texture = NoisetexR32f::new(64, 64, 1);
+        texture.fill(|info, pixel, xyz| {
+            let uv = xyz.truncate().as_vec2() / info.size().truncate().as_vec2();
+            *pixel = sampler.sample_2d(uv).into();
+        });
+        texture
+    }
+
+    // Against the threshold `generate --check-seams` warns at.
+    #[test]
+    fn seam_check_flags_textures_that_dont_tile() {
+        let tiling = perlin_texture(4.0).seam_check();
+        assert!(tiling.worst_ratio() < 2.0, "whole frequency: {tiling:?}");
+        assert!(tiling.z.is_none());
+
+        let broken = perlin_texture(4.5).seam_check();
+        assert!(broken.worst_ratio() > 2.0, "half frequency: {broken:?}");
+    }
+
+    // The samplers are built once and shared by every worker, including the state they initialize
+    // on first use, so the texture can't depend on how the pixels are scheduled.
+    #[test]
+    fn parallel_fill_is_deterministic() {
+        let fbm = FbmSampler::builder()
+            .sampler(PerlinSampler::builder().frequency(3.0).seed(2).build())
+            .octaves(4.0)
+            .mode(FbmMode::RidgedMultifractal)
+            .rotate_octaves(true)
+            .build();
+        let blue_noise = BlueNoiseSampler::builder().size(glam::uvec3(8, 8, 4)).seed(2).build();
+
+        let fill = || {
+            let mut texture = NoisetexR32f::new(32, 32, 4);
+            texture.fill(|info, pixel, xyz| {
+                let uvw = xyz.as_vec3() / info.size().as_vec3();
+                *pixel = (0.5 * (fbm.sample_3d(uvw) + blue_noise.sample_3d(uvw))).into();
+            });
+            texture.to_bytes()
+        };
+
+        let parallel = fill();
+        assert_eq!(fill(), parallel, "a second parallel fill differs");
+
+        let serial = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap().install(fill);
+        assert_eq!(serial, parallel, "a single-threaded fill differs");
+    }
+
+    #[test]
+    fn u16_colors_use_the_full_range() {
+        assert_eq!(ToColor::<u16>::to_color(0.0), 0);
+        assert_eq!(ToColor::<u16>::to_color(0.5), 0x7FFF);
+        assert_eq!(ToColor::<u16>::to_color(1.0), 0xFFFF);
+    }
+
+    #[test]
+    fn big_endian_bytes_swap_each_component() {
+        let mut texture = NoisetexR16::new(3, 1, 1);
+        texture.fill(|_info, pixel, xyz| *pixel = [0.0, 0.5, 1.0][xyz.x as usize].into());
+
+        let mut bytes = texture.to_bytes();
+        assert_eq!(bytes, [0x00, 0x00, 0xFF, 0x7F, 0xFF, 0xFF]);
+
+        Endianness::Big.reorder(&mut bytes, R16::COMPONENT.size());
+        assert_eq!(bytes, [0x00, 0x00, 0x7F, 0xFF, 0xFF, 0xFF]);
+
+        // Single bytes have no order to swap.
+        let mut texture = NoisetexR8::new(2, 1, 1);
+        texture.fill(|_info, pixel, xyz| *pixel = [0.0, 1.0][xyz.x as usize].into());
+        let mut bytes = texture.to_bytes();
+        Endianness::Big.reorder(&mut bytes, R8::COMPONENT.size());
+        assert_eq!(bytes, [0x00, 0xFF]);
+    }
+
+    /// A 2 by 3 texture of `depth` slices, each of a single value of its own.
+    fn sliced_texture(depth: u32) -> NoisetexR8 {
+        let mut texture = NoisetexR8::new(2, 3, depth);
+        texture.fill(|_info, pixel, xyz| *pixel = slice_value(xyz.z).into());
+        texture
+    }
+
+    fn slice_value(slice: u32) -> f32 {
+        (slice + 1) as f32 / 8.0
+    }
+
+    fn scratch_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir()
+            .join(format!("noisetex-tests-{}", std::process::id()))
+            .join(name)
+    }
+
+    #[test]
+    fn atlas_puts_each_slice_in_its_own_cell() {
+        // 5 slices leave the last cell of 3 columns empty, no columns are clamped to one and more
+        // columns than slices to a single row.
+        for (columns, expected_columns) in [(3, 3), (1, 1), (0, 1), (9, 5)] {
+            let texture = sliced_texture(5);
+            let path = scratch_path(&format!("atlas-{columns}.png"));
+            texture.save_as_atlas(&path, columns).unwrap();
+
+            let atlas = image::open(&path).unwrap().to_luma8();
+            std::fs::remove_file(&path).unwrap();
+            let rows = 5u32.div_ceil(expected_columns);
+            assert_eq!(atlas.dimensions(), (2 * expected_columns, 3 * rows), "{columns} columns");
+
+            for (x, y, pixel) in atlas.enumerate_pixels() {
+                let cell = (x / 2) + (y / 3) * expected_columns;
+                let expected = if cell < 5 { ToColor::<u8>::to_color(slice_value(cell)) } else { 0 };
+                assert_eq!(pixel.0[0], expected, "{columns} columns at {x}, {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn slice_image_holds_only_that_slice() {
+        let texture = sliced_texture(4);
+        for slice in 0..4 {
+            let path = scratch_path(&format!("slice-{slice}.png"));
+            texture.save_slice_as_image(&path, slice).unwrap();
+
+            let image = image::open(&path).unwrap().to_luma8();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(image.dimensions(), (2, 3));
+            let expected = ToColor::<u8>::to_color(slice_value(slice));
+            assert!(image.pixels().all(|pixel| pixel.0[0] == expected), "slice {slice}");
+        }
+    }
+
+    #[test]
+    fn default_atlas_is_nearly_square() {
+        for (depth, columns) in [(0, 1), (1, 1), (2, 2), (5, 3), (16, 4), (17, 5)] {
+            assert_eq!(NoisetexR8::default_atlas_columns(depth), columns, "depth {depth}");
+        }
     }
 }
@@ -0,0 +1,274 @@
+//! Mappings from texture pixels onto the unit sphere, for sky domes and other spherical domains.
+
+use serde::{Deserialize, Serialize};
+
+/// How the pixels of a texture map onto the sampled domain.
+///
+/// Spherical projections sample the 3D samplers on the sphere inscribed in the unit cube, so the
+/// result is seamless all around however the sphere is unwrapped. They render a single layer and
+/// always sample in 3D.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Projection {
+    /// Pixels map straight onto the texture volume.
+    #[default]
+    Planar,
+    /// Longitude along x and latitude along y, the north pole at the top.
+    Equirectangular,
+    /// Six square faces of `width` by `height` pixels, laid out as `layout` says.
+    Cubemap {
+        #[serde(default)]
+        layout: CubemapLayout,
+    },
+}
+impl Projection {
+    /// The textures a render is split into, each filled on its own.
+    pub fn surfaces(&self) -> Vec<Surface> {
+        match self {
+            Projection::Planar => vec![Surface::Volume],
+            Projection::Equirectangular => vec![Surface::Equirectangular],
+            Projection::Cubemap {
+                layout: CubemapLayout::Faces,
+            } => CubeFace::ALL.into_iter().map(Surface::Face).collect(),
+            Projection::Cubemap {
+                layout: CubemapLayout::Cross,
+            } => vec![Surface::Cross],
+            Projection::Cubemap {
+                layout: CubemapLayout::Strip,
+            } => vec![Surface::Strip],
+        }
+    }
+
+    /// The single texture a preview shows, the horizontal cross for cubemaps.
+    pub fn preview_surface(&self) -> Surface {
+        match self {
+            Projection::Planar => Surface::Volume,
+            Projection::Equirectangular => Surface::Equirectangular,
+            Projection::Cubemap { .. } => Surface::Cross,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CubemapLayout {
+    /// Six textures, with `{face}` in the output path replaced by the face name.
+    #[default]
+    Faces,
+    /// A horizontal cross four faces wide and three high, `+y` above and `-y` below `+z`.
+    Cross,
+    /// One row of the faces in the order `+x`, `-x`, `+y`, `-y`, `+z`, `-z`.
+    Strip,
+}
+
+/// A cubemap face, in the usual order of graphics APIs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+impl CubeFace {
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+
+    /// The name used in output paths.
+    pub fn name(&self) -> &'static str {
+        match self {
+            CubeFace::PositiveX => "px",
+            CubeFace::NegativeX => "nx",
+            CubeFace::PositiveY => "py",
+            CubeFace::NegativeY => "ny",
+            CubeFace::PositiveZ => "pz",
+            CubeFace::NegativeZ => "nz",
+        }
+    }
+
+    /// The direction through the point `st` of the face, both in -1..1 with t pointing down, as in
+    /// the OpenGL and Direct3D cubemap conventions.
+    pub fn direction(&self, st: glam::Vec2) -> glam::Vec3 {
+        let glam::Vec2 { x: s, y: t } = st;
+
+        let direction = match self {
+            CubeFace::PositiveX => glam::vec3(1.0, -t, -s),
+            CubeFace::NegativeX => glam::vec3(-1.0, -t, s),
+            CubeFace::PositiveY => glam::vec3(s, 1.0, t),
+            CubeFace::NegativeY => glam::vec3(s, -1.0, -t),
+            CubeFace::PositiveZ => glam::vec3(s, -t, 1.0),
+            CubeFace::NegativeZ => glam::vec3(-s, -t, -1.0),
+        };
+
+        direction.normalize()
+    }
+}
+
+/// One texture of a render, see [`Projection::surfaces`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Surface {
+    Volume,
+    Equirectangular,
+    Face(CubeFace),
+    Cross,
+    Strip,
+}
+impl Surface {
+    /// The texture size, given the recipe's `width` by `height` by `depth`, the size of one face
+    /// for cubemaps.
+    pub fn size(&self, width: u32, height: u32, depth: u32) -> glam::UVec3 {
+        match self {
+            Surface::Volume => glam::uvec3(width, height, depth),
+            Surface::Equirectangular | Surface::Face(_) => glam::uvec3(width, height, 1),
+            Surface::Cross => glam::uvec3(4 * width, 3 * height, 1),
+            Surface::Strip => glam::uvec3(6 * width, height, 1),
+        }
+    }
+
+    /// The face a surface is limited to, for output paths.
+    pub fn face(&self) -> Option<CubeFace> {
+        match self {
+            Surface::Face(face) => Some(*face),
+            _ => None,
+        }
+    }
+
+    /// The unit direction pixel `pixel` looks along, `None` for planar textures and the empty parts
+    /// of a cross. `face_size` is the recipe's `width` by `height`.
+    pub fn direction(&self, pixel: glam::UVec2, face_size: glam::UVec2) -> Option<glam::Vec3> {
+        let face_st = |face: CubeFace, pixel: glam::UVec2| {
+            let st = (pixel.as_vec2() + 0.5) / face_size.as_vec2() * 2.0 - 1.0;
+            face.direction(st)
+        };
+
+        match self {
+            Surface::Volume => None,
+            Surface::Equirectangular => {
+                let uv = (pixel.as_vec2() + 0.5) / face_size.as_vec2();
+                let longitude = (uv.x * 2.0 - 1.0) * std::f32::consts::PI;
+                let latitude = (0.5 - uv.y) * std::f32::consts::PI;
+
+                Some(glam::vec3(
+                    latitude.cos() * longitude.sin(),
+                    latitude.sin(),
+                    latitude.cos() * longitude.cos(),
+                ))
+            }
+            Surface::Face(face) => Some(face_st(*face, pixel)),
+            Surface::Cross => {
+                let cell = pixel / face_size;
+                let face = match (cell.x, cell.y) {
+                    (1, 0) => CubeFace::PositiveY,
+                    (0, 1) => CubeFace::NegativeX,
+                    (1, 1) => CubeFace::PositiveZ,
+                    (2, 1) => CubeFace::PositiveX,
+                    (3, 1) => CubeFace::NegativeZ,
+                    (1, 2) => CubeFace::NegativeY,
+                    _ => return None,
+                };
+
+                Some(face_st(face, pixel % face_size))
+            }
+            Surface::Strip => {
+                let face = CubeFace::ALL[(pixel.x / face_size.x) as usize];
+                Some(face_st(face, pixel % face_size))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FACE_SIZE: glam::UVec2 = glam::uvec2(16, 16);
+
+    fn pixels(surface: Surface) -> impl Iterator<Item = glam::UVec2> {
+        let size = surface.size(FACE_SIZE.x, FACE_SIZE.y, 1);
+        (0..size.y).flat_map(move |y| (0..size.x).map(move |x| glam::uvec2(x, y)))
+    }
+
+    #[test]
+    fn faces_share_their_edges() {
+        let edge = |face: CubeFace| {
+            (0..=8).flat_map(move |step| {
+                let along = step as f32 / 4.0 - 1.0;
+                [
+                    glam::vec2(-1.0, along),
+                    glam::vec2(1.0, along),
+                    glam::vec2(along, -1.0),
+                    glam::vec2(along, 1.0),
+                ]
+                .map(|st| face.direction(st))
+            })
+        };
+
+        for face in CubeFace::ALL {
+            for direction in edge(face) {
+                let shared = CubeFace::ALL
+                    .into_iter()
+                    .filter(|other| *other != face)
+                    .any(|other| edge(other).any(|other| other.distance(direction) < 1e-5));
+                assert!(shared, "{direction} on the edge of {face:?} isn't on any other face");
+            }
+        }
+    }
+
+    #[test]
+    fn directions_are_unit_length() {
+        let faces = CubeFace::ALL.map(Surface::Face);
+        for surface in [Surface::Equirectangular, Surface::Cross, Surface::Strip].iter().chain(&faces) {
+            for pixel in pixels(*surface) {
+                if let Some(direction) = surface.direction(pixel, FACE_SIZE) {
+                    assert!(direction.is_normalized(), "{surface:?} at {pixel} looks along {direction}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn neighbours_in_the_cross_look_along_nearby_directions() {
+        // Neighbouring pixel centres are a pixel apart, at most 2 / 16 of a face.
+        let max_distance = 2.0 / FACE_SIZE.x as f32;
+
+        for pixel in pixels(Surface::Cross) {
+            let Some(direction) = Surface::Cross.direction(pixel, FACE_SIZE) else {
+                continue;
+            };
+            for neighbour in [pixel + glam::UVec2::X, pixel + glam::UVec2::Y] {
+                if let Some(other) = Surface::Cross.direction(neighbour, FACE_SIZE) {
+                    let distance = direction.distance(other);
+                    assert!(distance < max_distance, "{pixel} and {neighbour} are {distance} apart");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn strip_matches_the_faces() {
+        for pixel in pixels(Surface::Strip) {
+            let face = Surface::Face(CubeFace::ALL[(pixel.x / FACE_SIZE.x) as usize]);
+            let expected = face.direction(pixel % FACE_SIZE, FACE_SIZE);
+            assert_eq!(Surface::Strip.direction(pixel, FACE_SIZE), expected, "strip at {pixel}");
+        }
+    }
+
+    #[test]
+    fn empty_cells_of_the_cross_have_no_direction() {
+        let filled = [(1, 0), (0, 1), (1, 1), (2, 1), (3, 1), (1, 2)];
+
+        for pixel in pixels(Surface::Cross) {
+            let cell = pixel / FACE_SIZE;
+            let direction = Surface::Cross.direction(pixel, FACE_SIZE);
+            assert_eq!(direction.is_some(), filled.contains(&(cell.x, cell.y)), "cross at {pixel}");
+        }
+        assert_eq!(Surface::Volume.direction(glam::UVec2::ZERO, FACE_SIZE), None);
+    }
+}
@@ -56,4 +56,8 @@ pub fn pcg_44(mut v: glam::UVec4) -> glam::UVec4 {
     v.w = v.w.wrapping_add(v.y.wrapping_mul(v.z));
 
     v
-}
\ No newline at end of file
+}
+/// Hashes a 4D lattice cell together with a seed, folding the seed into the last component.
+pub fn pcg_54(v: glam::UVec4, seed: u32) -> glam::UVec4 {
+    pcg_44(glam::uvec4(v.x, v.y, v.z, pcg_11(v.w.wrapping_add(pcg_11(seed)))))
+}
@@ -18,4 +18,13 @@ pub fn unit_vector_23(r: glam::UVec2) -> glam::Vec3 {
 
 pub fn unit_vector_13(r: u32) -> glam::Vec3 {
     unit_vector_23(glam::uvec2(r, pcg_11(r)))
-}
\ No newline at end of file
+}
+/// A uniformly distributed point on the unit 3-sphere, as two circles whose radii trade off.
+pub fn unit_vector_34(r: glam::UVec3) -> glam::Vec4 {
+    let theta = (r.x as f32) / (u32::MAX as f32) * 2.0 * f32::consts::PI;
+    let phi = (r.y as f32) / (u32::MAX as f32) * 2.0 * f32::consts::PI;
+    let split = (r.z as f32) / (u32::MAX as f32);
+
+    let (inner, outer) = ((1.0 - split).sqrt(), split.sqrt());
+    glam::Vec4::new(theta.cos() * inner, theta.sin() * inner, phi.cos() * outer, phi.sin() * outer)
+}
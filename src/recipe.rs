@@ -0,0 +1,1828 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use glam::Vec3Swizzles;
+use serde::{Deserialize, Serialize};
+
+use crate::container::Provenance;
+use crate::export::{Endianness, TextureLayout};
+use crate::noisetex::{
+    ComponentType, Noisetex, PixelType, R16f, R32f, Rg16, Rg16f, Rg8, Rgb16, Rgb8, Rgba16,
+    Rgba16f, Rgba8, SeamCheck, R16, R8,
+};
+use crate::projection::{CubeFace, Projection, Surface};
+use crate::samplers::alligator::AlligatorSampler;
+use crate::samplers::blue_noise::BlueNoiseSampler;
+use crate::samplers::combine::{
+    CombineMode, CombineSampler, ConstantSampler, MixSampler, Transform, TransformSampler,
+};
+use crate::samplers::fbm::{FbmMode, FbmSampler};
+use crate::samplers::gabor::{GaborOrientation, GaborSampler};
+use crate::samplers::perlin::{PerlinMode, PerlinSampler};
+use crate::samplers::perlin_worley::PerlinWorleySampler;
+use crate::samplers::simplex::{SimplexMode, SimplexSampler, SimplexVariant};
+use crate::samplers::value::{ValueInterpolation, ValueSampler};
+use crate::samplers::vector_field::{
+    CurlSampler, GradientCurlSampler, VectorFieldFbmSampler, VectorFieldSampler,
+};
+use crate::samplers::warp::{Axis, DomainWarpSampler, VectorComponentSampler, VectorFromScalarsSampler};
+use crate::samplers::worley::{WorleyMetric, WorleyMode, WorleySampler};
+use crate::samplers::{GradientSampler, NoiseSampler, Smoothing};
+use crate::util::{MappingFn, Normalized};
+
+/// Recipes bundled into the binary, by name.
+pub const PRESETS: &[(&str, &str)] = &[
+    ("lf_cloudmap", include_str!("../recipes/lf_cloudmap.toml")),
+    ("hf_cloudmap", include_str!("../recipes/hf_cloudmap.toml")),
+    (
+        "noise_composite",
+        include_str!("../recipes/noise_composite.toml"),
+    ),
+    ("curl_2d", include_str!("../recipes/curl_2d.toml")),
+    ("blue_noise", include_str!("../recipes/blue_noise.toml")),
+];
+
+/// A texture description loaded from a TOML or RON file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recipe {
+    #[serde(default)]
+    pub description: String,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default = "default_depth")]
+    pub depth: u32,
+    pub format: PixelFormat,
+    /// How many textures to generate, passed to the output path as `{index}`.
+    #[serde(default = "default_count")]
+    pub count: u32,
+    /// Whether the seed is offset by the texture index, so every texture in the set differs.
+    #[serde(default = "default_true")]
+    pub seed_per_index: bool,
+    pub output: OutputRecipe,
+    #[serde(default)]
+    pub channels: ChannelsRecipe,
+    /// Renders a looping animation instead of a still texture.
+    pub animation: Option<AnimationRecipe>,
+    #[serde(default)]
+    pub projection: Projection,
+}
+
+/// The files one texture was written to, see [`Recipe::generate_checked`].
+#[derive(Debug, Clone)]
+pub struct GeneratedTexture {
+    pub paths: Vec<PathBuf>,
+    /// How well the texture tiles, if checked.
+    pub seams: Option<SeamCheck>,
+}
+
+/// Frames of a texture evolving over time and looping back to the start.
+///
+/// 3D channels sample 4D noise with time as the fourth axis, 2D channels 3D noise with time as the
+/// third.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnimationRecipe {
+    /// Frames evenly spaced over one loop.
+    pub frames: u32,
+    /// Whether the frames are written as consecutive slices of a single texture, instead of a file
+    /// each with `{frame}` in the output path.
+    #[serde(default)]
+    pub stack: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "snake_case")]
+pub enum PixelFormat {
+    Rgba8,
+    Rgb8,
+    Rg8,
+    R8,
+    /// 16 bits per channel, written as 16-bit PNGs.
+    Rgba16,
+    Rgb16,
+    Rg16,
+    R16,
+    /// Half floats, which keep signed and out of range values instead of clamping them.
+    R16f,
+    Rg16f,
+    Rgba16f,
+    R32f,
+}
+impl PixelFormat {
+    pub const ALL: [PixelFormat; 12] = [
+        PixelFormat::Rgba8,
+        PixelFormat::Rgb8,
+        PixelFormat::Rg8,
+        PixelFormat::R8,
+        PixelFormat::Rgba16,
+        PixelFormat::Rgb16,
+        PixelFormat::Rg16,
+        PixelFormat::R16,
+        PixelFormat::R16f,
+        PixelFormat::Rg16f,
+        PixelFormat::Rgba16f,
+        PixelFormat::R32f,
+    ];
+
+    pub fn channels(&self) -> usize {
+        match self {
+            PixelFormat::Rgba8 => Rgba8::CHANNELS,
+            PixelFormat::Rgb8 => Rgb8::CHANNELS,
+            PixelFormat::Rg8 => Rg8::CHANNELS,
+            PixelFormat::R8 => R8::CHANNELS,
+            PixelFormat::Rgba16 => Rgba16::CHANNELS,
+            PixelFormat::Rgb16 => Rgb16::CHANNELS,
+            PixelFormat::Rg16 => Rg16::CHANNELS,
+            PixelFormat::R16 => R16::CHANNELS,
+            PixelFormat::R16f => R16f::CHANNELS,
+            PixelFormat::Rg16f => Rg16f::CHANNELS,
+            PixelFormat::Rgba16f => Rgba16f::CHANNELS,
+            PixelFormat::R32f => R32f::CHANNELS,
+        }
+    }
+
+    pub fn component(&self) -> ComponentType {
+        match self {
+            PixelFormat::Rgba8 => Rgba8::COMPONENT,
+            PixelFormat::Rgb8 => Rgb8::COMPONENT,
+            PixelFormat::Rg8 => Rg8::COMPONENT,
+            PixelFormat::R8 => R8::COMPONENT,
+            PixelFormat::Rgba16 => Rgba16::COMPONENT,
+            PixelFormat::Rgb16 => Rgb16::COMPONENT,
+            PixelFormat::Rg16 => Rg16::COMPONENT,
+            PixelFormat::R16 => R16::COMPONENT,
+            PixelFormat::R16f => R16f::COMPONENT,
+            PixelFormat::Rg16f => Rg16f::COMPONENT,
+            PixelFormat::Rgba16f => Rgba16f::COMPONENT,
+            PixelFormat::R32f => R32f::COMPONENT,
+        }
+    }
+
+    /// The format of pixels of `channels` components of `component`, if there is one.
+    pub fn from_layout(channels: usize, component: ComponentType) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.channels() == channels && format.component() == component)
+    }
+
+    pub fn bytes_per_channel(&self) -> usize {
+        self.component().size()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputRecipe {
+    /// Output path relative to the output directory, `{index}` is replaced by the texture index and
+    /// `{frame}` by the frame of an animation.
+    pub path: String,
+    #[serde(default)]
+    pub kind: OutputKind,
+    /// Columns of the slice grid volumes are saved as images in, the most square grid if unset.
+    pub columns: Option<u32>,
+    /// Whether DDS and KTX2 volumes are 3D textures or 2D texture arrays.
+    #[serde(default)]
+    pub layout: TextureLayout,
+    /// Byte order of the components of binary and container outputs.
+    #[serde(default)]
+    pub endianness: Endianness,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputKind {
+    /// A single image, volumes as an atlas of their z-slices.
+    #[default]
+    Image,
+    Binary,
+    /// The raw pixels behind a header with their size and format, the seed and the recipe, see
+    /// [`crate::container`].
+    Container,
+    /// A DDS file with a DX10 header, 8 and 16-bit RGB padded to RGBA.
+    Dds,
+    Ktx2,
+    /// An OpenEXR file, volumes with a part per z-slice. Float formats keep their full range.
+    Exr,
+    /// An image per z-slice, with `{slice}` in the path replaced by the slice index.
+    Slices,
+    /// Both the atlas of [`OutputKind::Image`] and the sequence of [`OutputKind::Slices`], the
+    /// atlas at the path without `{slice}`.
+    ImageAndSlices,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChannelsRecipe {
+    pub r: Option<ChannelRecipe>,
+    pub g: Option<ChannelRecipe>,
+    pub b: Option<ChannelRecipe>,
+    pub a: Option<ChannelRecipe>,
+}
+impl ChannelsRecipe {
+    fn get(&self, index: usize) -> Option<&ChannelRecipe> {
+        match index {
+            0 => self.r.as_ref(),
+            1 => self.g.as_ref(),
+            2 => self.b.as_ref(),
+            3 => self.a.as_ref(),
+            _ => None,
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&'static str, &ChannelRecipe)> {
+        [("r", &self.r), ("g", &self.g), ("b", &self.b), ("a", &self.a)]
+            .into_iter()
+            .filter_map(|(name, channel)| Some((name, channel.as_ref()?)))
+    }
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = (&'static str, &mut ChannelRecipe)> {
+        [("r", &mut self.r), ("g", &mut self.g), ("b", &mut self.b), ("a", &mut self.a)]
+            .into_iter()
+            .filter_map(|(name, channel)| Some((name, channel.as_mut()?)))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelRecipe {
+    pub sampler: SamplerRecipe,
+    #[serde(default)]
+    pub dimensions: SampleDimensions,
+    /// Transforms applied in order to the sampled value.
+    #[serde(default)]
+    pub post: Vec<Transform>,
+    /// Whether the channel is stretched to the full 0-1 range once the texture is filled, by
+    /// default only for formats that clamp to 0..1, so float formats keep values as sampled.
+    pub normalize: Option<bool>,
+}
+impl ChannelRecipe {
+    fn normalizes(&self, format: PixelFormat) -> bool {
+        self.normalize.unwrap_or(!format.component().is_float())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum SampleDimensions {
+    #[serde(rename = "2d")]
+    Two,
+    #[default]
+    #[serde(rename = "3d")]
+    Three,
+}
+
+/// Sampler seeds are offsets from the seed the recipe is generated with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SamplerRecipe {
+    Perlin {
+        frequency: f32,
+        #[serde(default)]
+        seed: u32,
+        period: Option<u32>,
+        #[serde(default)]
+        smoothing: Smoothing,
+        #[serde(default)]
+        mode: PerlinMode,
+        #[serde(default)]
+        vary_gradient_magnitudes: bool,
+    },
+    Simplex {
+        frequency: f32,
+        #[serde(default)]
+        seed: u32,
+        period: Option<u32>,
+        #[serde(default)]
+        smoothing: Smoothing,
+        #[serde(default)]
+        mode: SimplexMode,
+        #[serde(default)]
+        variant: SimplexVariant,
+    },
+    Value {
+        frequency: f32,
+        #[serde(default)]
+        seed: u32,
+        period: Option<u32>,
+        #[serde(default)]
+        smoothing: Smoothing,
+        #[serde(default)]
+        interpolation: ValueInterpolation,
+    },
+    Worley {
+        frequency: f32,
+        #[serde(default)]
+        seed: u32,
+        period: Option<u32>,
+        #[serde(default)]
+        smoothing: Smoothing,
+        #[serde(default)]
+        mode: WorleyMode,
+        #[serde(default)]
+        metric: WorleyMetric,
+        #[serde(default = "default_one_u32")]
+        radius: u32,
+        #[serde(default = "default_one")]
+        jitter: f32,
+        #[serde(default)]
+        points_per_cell: Option<f32>,
+    },
+    Alligator {
+        frequency: f32,
+        #[serde(default)]
+        seed: u32,
+        period: Option<u32>,
+        #[serde(default)]
+        smoothing: Smoothing,
+        #[serde(default = "default_true")]
+        randomize_cell_strength: bool,
+        #[serde(default = "default_one")]
+        jitter: f32,
+        #[serde(default)]
+        points_per_cell: Option<f32>,
+    },
+    /// Oriented, band-limited noise from a sparse convolution of Gabor kernels.
+    Gabor {
+        frequency: f32,
+        #[serde(default)]
+        seed: u32,
+        period: Option<u32>,
+        #[serde(default)]
+        smoothing: Smoothing,
+        /// Cycles of each kernel's stripes per lattice cell.
+        #[serde(default = "default_two")]
+        kernel_frequency: f32,
+        #[serde(default = "default_gabor_bandwidth")]
+        bandwidth: f32,
+        #[serde(default = "default_impulses_per_cell")]
+        impulses_per_cell: f32,
+        #[serde(default)]
+        orientation: GaborOrientationRecipe,
+        #[serde(default)]
+        spread: f32,
+    },
+    PerlinWorley {
+        frequency: f32,
+        #[serde(default)]
+        seed: u32,
+        period: Option<u32>,
+        #[serde(default = "default_perlin_octaves")]
+        perlin_octaves: u32,
+        #[serde(default = "default_worley_frequencies")]
+        worley_frequencies: [f32; 3],
+        #[serde(default = "default_one")]
+        blend: f32,
+        #[serde(default)]
+        smoothing: Smoothing,
+    },
+    /// A void-and-cluster blue-noise pattern, of the texture size unless `size` is set.
+    BlueNoise {
+        #[serde(default)]
+        seed: u32,
+        #[serde(default = "default_blue_noise_sigma")]
+        sigma: f32,
+        /// Makes the 3D pattern spatiotemporal, blue along z with this sigma on its own.
+        temporal_sigma: Option<f32>,
+        size: Option<[u32; 3]>,
+    },
+    Fbm {
+        sampler: Box<SamplerRecipe>,
+        octaves: f32,
+        #[serde(default = "default_two")]
+        decay: f32,
+        #[serde(default = "default_two")]
+        lacunarity: f32,
+        #[serde(default)]
+        smoothing: Smoothing,
+        #[serde(default)]
+        mode: FbmMode,
+        #[serde(default = "default_one")]
+        offset: f32,
+        #[serde(default = "default_two")]
+        gain: f32,
+        /// Amplitudes of the first octaves, in place of `decay`.
+        amplitudes: Option<Vec<f32>>,
+        #[serde(default)]
+        rotate_octaves: bool,
+        #[serde(default)]
+        offset_octaves: bool,
+        min_frequency: Option<f32>,
+        max_frequency: Option<f32>,
+    },
+    Constant {
+        value: f32,
+    },
+    Add {
+        a: Box<SamplerRecipe>,
+        b: Box<SamplerRecipe>,
+    },
+    Subtract {
+        a: Box<SamplerRecipe>,
+        b: Box<SamplerRecipe>,
+    },
+    Multiply {
+        a: Box<SamplerRecipe>,
+        b: Box<SamplerRecipe>,
+    },
+    Min {
+        a: Box<SamplerRecipe>,
+        b: Box<SamplerRecipe>,
+    },
+    Max {
+        a: Box<SamplerRecipe>,
+        b: Box<SamplerRecipe>,
+    },
+    Mix {
+        a: Box<SamplerRecipe>,
+        b: Box<SamplerRecipe>,
+        alpha: Box<SamplerRecipe>,
+    },
+    Invert {
+        sampler: Box<SamplerRecipe>,
+    },
+    Pow {
+        sampler: Box<SamplerRecipe>,
+        exponent: f32,
+    },
+    Smooth {
+        sampler: Box<SamplerRecipe>,
+        smoothing: Smoothing,
+    },
+    Remap {
+        sampler: Box<SamplerRecipe>,
+        from: [f32; 2],
+        to: [f32; 2],
+    },
+    Clamp {
+        sampler: Box<SamplerRecipe>,
+        min: f32,
+        max: f32,
+    },
+    DomainWarp {
+        sampler: Box<SamplerRecipe>,
+        warp: Box<VectorSamplerRecipe>,
+        #[serde(default = "default_one")]
+        strength: f32,
+        #[serde(default = "default_one_u32")]
+        iterations: u32,
+        /// Size the warped coordinates are wrapped at, defaults to the texture size.
+        size: Option<[f32; 3]>,
+    },
+    /// One component of a vector sampler, scaled and mapped from -1..1 to 0..1, or left as it is
+    /// if `signed`, for float formats.
+    VectorComponent {
+        sampler: Box<VectorSamplerRecipe>,
+        axis: Axis,
+        #[serde(default = "default_one")]
+        scale: f32,
+        #[serde(default)]
+        signed: bool,
+    },
+}
+impl SamplerRecipe {
+    pub fn build(&self, seed: u32, texture_size: glam::Vec3) -> Box<dyn NoiseSampler<f32>> {
+        match self {
+            SamplerRecipe::Perlin {
+                frequency,
+                seed: seed_offset,
+                period,
+                smoothing,
+                mode,
+                vary_gradient_magnitudes,
+            } => Box::new(
+                PerlinSampler::builder()
+                    .frequency(*frequency)
+                    .seed(seed.wrapping_add(*seed_offset))
+                    .maybe_period(*period)
+                    .smoothing(*smoothing)
+                    .mode(*mode)
+                    .vary_gradient_magnitudes(*vary_gradient_magnitudes)
+                    .build(),
+            ),
+            SamplerRecipe::Simplex {
+                frequency,
+                seed: seed_offset,
+                period,
+                smoothing,
+                mode,
+                variant,
+            } => Box::new(
+                SimplexSampler::builder()
+                    .frequency(*frequency)
+                    .seed(seed.wrapping_add(*seed_offset))
+                    .maybe_period(*period)
+                    .smoothing(*smoothing)
+                    .mode(*mode)
+                    .variant(*variant)
+                    .build(),
+            ),
+            SamplerRecipe::Value {
+                frequency,
+                seed: seed_offset,
+                period,
+                smoothing,
+                interpolation,
+            } => Box::new(
+                ValueSampler::builder()
+                    .frequency(*frequency)
+                    .seed(seed.wrapping_add(*seed_offset))
+                    .maybe_period(*period)
+                    .smoothing(*smoothing)
+                    .interpolation(*interpolation)
+                    .build(),
+            ),
+            SamplerRecipe::Worley {
+                frequency,
+                seed: seed_offset,
+                period,
+                smoothing,
+                mode,
+                metric,
+                radius,
+                jitter,
+                points_per_cell,
+            } => Box::new(
+                WorleySampler::builder()
+                    .frequency(*frequency)
+                    .seed(seed.wrapping_add(*seed_offset))
+                    .maybe_period(*period)
+                    .smoothing(*smoothing)
+                    .mode(*mode)
+                    .metric(*metric)
+                    .radius(*radius)
+                    .jitter(*jitter)
+                    .maybe_points_per_cell(*points_per_cell)
+                    .build(),
+            ),
+            SamplerRecipe::Alligator {
+                frequency,
+                seed: seed_offset,
+                period,
+                smoothing,
+                randomize_cell_strength,
+                jitter,
+                points_per_cell,
+            } => Box::new(
+                AlligatorSampler::builder()
+                    .frequency(*frequency)
+                    .seed(seed.wrapping_add(*seed_offset))
+                    .maybe_period(*period)
+                    .smoothing(*smoothing)
+                    .randomize_cell_strength(*randomize_cell_strength)
+                    .jitter(*jitter)
+                    .maybe_points_per_cell(*points_per_cell)
+                    .build(),
+            ),
+            SamplerRecipe::Gabor {
+                frequency,
+                seed: seed_offset,
+                period,
+                smoothing,
+                kernel_frequency,
+                bandwidth,
+                impulses_per_cell,
+                orientation,
+                spread,
+            } => Box::new(
+                GaborSampler::builder()
+                    .frequency(*frequency)
+                    .seed(seed.wrapping_add(*seed_offset))
+                    .maybe_period(*period)
+                    .smoothing(*smoothing)
+                    .kernel_frequency(*kernel_frequency)
+                    .bandwidth(*bandwidth)
+                    .impulses_per_cell(*impulses_per_cell)
+                    .orientation(orientation.build(seed, texture_size))
+                    .spread(*spread)
+                    .build(),
+            ),
+            SamplerRecipe::BlueNoise {
+                seed: seed_offset,
+                sigma,
+                temporal_sigma,
+                size,
+            } => Box::new(
+                BlueNoiseSampler::builder()
+                    .size(size.map_or(texture_size.as_uvec3(), glam::UVec3::from_array))
+                    .seed(seed.wrapping_add(*seed_offset))
+                    .sigma(*sigma)
+                    .maybe_temporal_sigma(*temporal_sigma)
+                    .build(),
+            ),
+            SamplerRecipe::PerlinWorley {
+                frequency,
+                seed: seed_offset,
+                period,
+                perlin_octaves,
+                worley_frequencies,
+                blend,
+                smoothing,
+            } => Box::new(
+                PerlinWorleySampler::builder()
+                    .frequency(*frequency)
+                    .seed(seed.wrapping_add(*seed_offset))
+                    .maybe_period(*period)
+                    .perlin_octaves(*perlin_octaves)
+                    .worley_frequencies(*worley_frequencies)
+                    .blend(*blend)
+                    .smoothing(*smoothing)
+                    .build(),
+            ),
+            SamplerRecipe::Fbm {
+                sampler,
+                octaves,
+                decay,
+                lacunarity,
+                smoothing,
+                mode,
+                offset,
+                gain,
+                amplitudes,
+                rotate_octaves,
+                offset_octaves,
+                min_frequency,
+                max_frequency,
+            } => Box::new(
+                FbmSampler::builder()
+                    .sampler(sampler.build(seed, texture_size))
+                    .octaves(*octaves)
+                    .decay(*decay)
+                    .lacunarity(*lacunarity)
+                    .smoothing(*smoothing)
+                    .mode(*mode)
+                    .offset(*offset)
+                    .gain(*gain)
+                    .maybe_amplitudes(amplitudes.clone())
+                    .rotate_octaves(*rotate_octaves)
+                    .offset_octaves(*offset_octaves)
+                    .maybe_min_frequency(*min_frequency)
+                    .maybe_max_frequency(*max_frequency)
+                    .build(),
+            ),
+            SamplerRecipe::Constant { value } => {
+                Box::new(ConstantSampler::builder().value(*value).build())
+            }
+            SamplerRecipe::Add { a, b } => Self::build_combine(a, b, CombineMode::Add, seed, texture_size),
+            SamplerRecipe::Subtract { a, b } => {
+                Self::build_combine(a, b, CombineMode::Subtract, seed, texture_size)
+            }
+            SamplerRecipe::Multiply { a, b } => {
+                Self::build_combine(a, b, CombineMode::Multiply, seed, texture_size)
+            }
+            SamplerRecipe::Min { a, b } => Self::build_combine(a, b, CombineMode::Min, seed, texture_size),
+            SamplerRecipe::Max { a, b } => Self::build_combine(a, b, CombineMode::Max, seed, texture_size),
+            SamplerRecipe::Mix { a, b, alpha } => Box::new(
+                MixSampler::builder()
+                    .a(a.build(seed, texture_size))
+                    .b(b.build(seed, texture_size))
+                    .alpha(alpha.build(seed, texture_size))
+                    .build(),
+            ),
+            SamplerRecipe::Invert { sampler } => {
+                Self::build_transform(sampler, Transform::Invert, seed, texture_size)
+            }
+            SamplerRecipe::Pow { sampler, exponent } => Self::build_transform(
+                sampler,
+                Transform::Pow {
+                    exponent: *exponent,
+                },
+                seed,
+                texture_size,
+            ),
+            SamplerRecipe::Smooth { sampler, smoothing } => Self::build_transform(
+                sampler,
+                Transform::Smooth {
+                    smoothing: *smoothing,
+                },
+                seed,
+                texture_size,
+            ),
+            SamplerRecipe::Remap { sampler, from, to } => Self::build_transform(
+                sampler,
+                Transform::Remap {
+                    from: *from,
+                    to: *to,
+                },
+                seed,
+                texture_size,
+            ),
+            SamplerRecipe::Clamp { sampler, min, max } => Self::build_transform(
+                sampler,
+                Transform::Clamp {
+                    min: *min,
+                    max: *max,
+                },
+                seed,
+                texture_size,
+            ),
+            SamplerRecipe::DomainWarp {
+                sampler,
+                warp,
+                strength,
+                iterations,
+                size,
+            } => Box::new(
+                DomainWarpSampler::builder()
+                    .sampler(sampler.build(seed, texture_size))
+                    .warp(warp.build(seed, texture_size))
+                    .size(size.map_or(texture_size, glam::Vec3::from_array))
+                    .strength(*strength)
+                    .iterations(*iterations)
+                    .build(),
+            ),
+            SamplerRecipe::VectorComponent {
+                sampler,
+                axis,
+                scale,
+                signed,
+            } => Box::new(
+                VectorComponentSampler::builder()
+                    .sampler(sampler.build(seed, texture_size))
+                    .axis(*axis)
+                    .scale(*scale)
+                    .signed(*signed)
+                    .build(),
+            ),
+        }
+    }
+
+    fn build_combine(
+        a: &SamplerRecipe,
+        b: &SamplerRecipe,
+        mode: CombineMode,
+        seed: u32,
+        texture_size: glam::Vec3,
+    ) -> Box<dyn NoiseSampler<f32>> {
+        Box::new(
+            CombineSampler::builder()
+                .a(a.build(seed, texture_size))
+                .b(b.build(seed, texture_size))
+                .mode(mode)
+                .build(),
+        )
+    }
+
+    fn build_transform(
+        sampler: &SamplerRecipe,
+        transform: Transform,
+        seed: u32,
+        texture_size: glam::Vec3,
+    ) -> Box<dyn NoiseSampler<f32>> {
+        Box::new(
+            TransformSampler::builder()
+                .sampler(sampler.build(seed, texture_size))
+                .transform(transform)
+                .build(),
+        )
+    }
+
+    /// Reports or rounds frequencies that keep the sampler from tiling, `scales` being what the
+    /// octaves above it multiply its frequency by.
+    fn check_tiling(&mut self, check: &mut TilingCheck, scales: &[f32]) {
+        match self {
+            SamplerRecipe::Perlin { frequency, period, .. } => check.lattice("perlin", frequency, *period, scales),
+            SamplerRecipe::Simplex { frequency, period, .. } => check.lattice("simplex", frequency, *period, scales),
+            SamplerRecipe::Value { frequency, period, .. } => check.lattice("value", frequency, *period, scales),
+            SamplerRecipe::Worley { frequency, period, .. } => check.lattice("worley", frequency, *period, scales),
+            SamplerRecipe::Alligator { frequency, period, .. } => {
+                check.lattice("alligator", frequency, *period, scales)
+            }
+            SamplerRecipe::Gabor {
+                frequency,
+                period,
+                orientation,
+                ..
+            } => {
+                check.lattice("gabor", frequency, *period, scales);
+                if let GaborOrientationRecipe::Field { sampler } = orientation {
+                    sampler.check_tiling(check, scales);
+                }
+            }
+            SamplerRecipe::PerlinWorley {
+                frequency,
+                period,
+                perlin_octaves,
+                worley_frequencies,
+                ..
+            } => {
+                for multiple in worley_frequencies.iter_mut() {
+                    check.multiple("perlin_worley worley frequency", multiple);
+                }
+
+                let mut layer_scales = octave_scales(scales, *perlin_octaves, 2.0);
+                for multiple in worley_frequencies.iter() {
+                    layer_scales.extend(scales.iter().map(|scale| scale * multiple));
+                }
+                check.lattice("perlin_worley", frequency, *period, &layer_scales);
+            }
+            SamplerRecipe::Fbm {
+                sampler,
+                octaves,
+                lacunarity,
+                ..
+            } => {
+                check.multiple("fbm lacunarity", lacunarity);
+                // Rotated octaves are rounded to multiples of the frequency over the period, which tile
+                // whenever the unrotated ones do.
+                sampler.check_tiling(check, &octave_scales(scales, octaves.ceil() as u32, *lacunarity));
+            }
+            SamplerRecipe::Constant { .. } | SamplerRecipe::BlueNoise { .. } => {}
+            SamplerRecipe::Add { a, b }
+            | SamplerRecipe::Subtract { a, b }
+            | SamplerRecipe::Multiply { a, b }
+            | SamplerRecipe::Min { a, b }
+            | SamplerRecipe::Max { a, b } => {
+                a.check_tiling(check, scales);
+                b.check_tiling(check, scales);
+            }
+            SamplerRecipe::Mix { a, b, alpha } => {
+                a.check_tiling(check, scales);
+                b.check_tiling(check, scales);
+                alpha.check_tiling(check, scales);
+            }
+            SamplerRecipe::Invert { sampler }
+            | SamplerRecipe::Pow { sampler, .. }
+            | SamplerRecipe::Smooth { sampler, .. }
+            | SamplerRecipe::Remap { sampler, .. }
+            | SamplerRecipe::Clamp { sampler, .. } => sampler.check_tiling(check, scales),
+            SamplerRecipe::DomainWarp { sampler, warp, .. } => {
+                sampler.check_tiling(check, scales);
+                warp.check_tiling(check, scales);
+            }
+            SamplerRecipe::VectorComponent { sampler, .. } => sampler.check_tiling(check, scales),
+        }
+    }
+
+    /// Adds the samplers in the tree that have no time axis when sampled in `dimensions`, and so
+    /// look the same in every frame of an animation, to `names`. `depth` is the texture depth.
+    ///
+    /// In 2D time is sampled as a third axis, which every sampler has. Blue noise only changes
+    /// along it with a pattern more than one slice deep.
+    fn timeless_samplers(&self, dimensions: SampleDimensions, depth: u32, names: &mut Vec<&'static str>) {
+        let three = matches!(dimensions, SampleDimensions::Three);
+        match self {
+            SamplerRecipe::Value { .. } if three => names.push("value"),
+            SamplerRecipe::Alligator { .. } if three => names.push("alligator"),
+            SamplerRecipe::Gabor { .. } if three => names.push("gabor"),
+            SamplerRecipe::Gabor {
+                orientation: GaborOrientationRecipe::Field { sampler },
+                ..
+            } => sampler.timeless_samplers(dimensions, depth, names),
+            SamplerRecipe::BlueNoise { size, .. } => {
+                if three || size.map_or(depth, |size| size[2]) <= 1 {
+                    names.push("blue_noise");
+                }
+            }
+            SamplerRecipe::Perlin { .. }
+            | SamplerRecipe::Simplex { .. }
+            | SamplerRecipe::Value { .. }
+            | SamplerRecipe::Worley { .. }
+            | SamplerRecipe::Alligator { .. }
+            | SamplerRecipe::Gabor { .. }
+            | SamplerRecipe::PerlinWorley { .. }
+            | SamplerRecipe::Constant { .. } => {}
+            SamplerRecipe::Add { a, b }
+            | SamplerRecipe::Subtract { a, b }
+            | SamplerRecipe::Multiply { a, b }
+            | SamplerRecipe::Min { a, b }
+            | SamplerRecipe::Max { a, b } => {
+                a.timeless_samplers(dimensions, depth, names);
+                b.timeless_samplers(dimensions, depth, names);
+            }
+            SamplerRecipe::Mix { a, b, alpha } => {
+                a.timeless_samplers(dimensions, depth, names);
+                b.timeless_samplers(dimensions, depth, names);
+                alpha.timeless_samplers(dimensions, depth, names);
+            }
+            SamplerRecipe::Fbm { sampler, .. }
+            | SamplerRecipe::Invert { sampler }
+            | SamplerRecipe::Pow { sampler, .. }
+            | SamplerRecipe::Smooth { sampler, .. }
+            | SamplerRecipe::Remap { sampler, .. }
+            | SamplerRecipe::Clamp { sampler, .. } => sampler.timeless_samplers(dimensions, depth, names),
+            SamplerRecipe::DomainWarp { sampler, warp, .. } => {
+                sampler.timeless_samplers(dimensions, depth, names);
+                warp.timeless_samplers(dimensions, depth, names);
+            }
+            SamplerRecipe::VectorComponent { sampler, .. } => sampler.timeless_samplers(dimensions, depth, names),
+        }
+    }
+}
+
+/// Which way the kernels of a [`SamplerRecipe::Gabor`] are oriented.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GaborOrientationRecipe {
+    #[default]
+    Random,
+    Fixed {
+        direction: [f32; 3],
+    },
+    /// Along a vector field, the xy part of it in 2D.
+    Field {
+        sampler: Box<VectorSamplerRecipe>,
+    },
+}
+impl GaborOrientationRecipe {
+    fn build(&self, seed: u32, texture_size: glam::Vec3) -> GaborOrientation<Box<dyn NoiseSampler<glam::Vec3>>> {
+        match self {
+            GaborOrientationRecipe::Random => GaborOrientation::Random,
+            GaborOrientationRecipe::Fixed { direction } => GaborOrientation::Fixed(glam::Vec3::from_array(*direction)),
+            GaborOrientationRecipe::Field { sampler } => GaborOrientation::Field(sampler.build(seed, texture_size)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VectorSamplerRecipe {
+    VectorField {
+        frequency: f32,
+        #[serde(default)]
+        seed: u32,
+        period: Option<u32>,
+        #[serde(default)]
+        smoothing: Smoothing,
+        bias: Option<[f32; 3]>,
+    },
+    VectorFieldFbm {
+        sampler: Box<VectorSamplerRecipe>,
+        octaves: u32,
+        #[serde(default = "default_two")]
+        persistence: f32,
+        #[serde(default = "default_two")]
+        lacunarity: f32,
+        #[serde(default)]
+        smoothing: Smoothing,
+    },
+    Curl {
+        sampler: Box<VectorSamplerRecipe>,
+        /// Size of the differentiation grid, defaults to the texture size.
+        size: Option<[f32; 3]>,
+    },
+    /// Builds a vector from three scalar samplers, each mapped from 0..1 to -1..1.
+    FromScalars {
+        x: Box<SamplerRecipe>,
+        y: Box<SamplerRecipe>,
+        z: Box<SamplerRecipe>,
+    },
+    /// Curl noise from the analytic gradients of a scalar potential.
+    GradientCurl {
+        potential: Box<PotentialRecipe>,
+    },
+}
+impl VectorSamplerRecipe {
+    pub fn build(&self, seed: u32, texture_size: glam::Vec3) -> Box<dyn NoiseSampler<glam::Vec3>> {
+        match self {
+            VectorSamplerRecipe::VectorField {
+                frequency,
+                seed: seed_offset,
+                period,
+                smoothing,
+                bias,
+            } => Box::new(
+                VectorFieldSampler::builder()
+                    .frequency(*frequency)
+                    .seed(seed.wrapping_add(*seed_offset))
+                    .maybe_period(*period)
+                    .smoothing(*smoothing)
+                    .maybe_bias(bias.map(glam::Vec3::from_array))
+                    .build(),
+            ),
+            VectorSamplerRecipe::VectorFieldFbm {
+                sampler,
+                octaves,
+                persistence,
+                lacunarity,
+                smoothing,
+            } => Box::new(
+                VectorFieldFbmSampler::builder()
+                    .sampler(sampler.build(seed, texture_size))
+                    .octaves(*octaves)
+                    .persistence(*persistence)
+                    .lacunarity(*lacunarity)
+                    .smoothing(*smoothing)
+                    .build(),
+            ),
+            VectorSamplerRecipe::Curl { sampler, size } => Box::new(
+                CurlSampler::builder()
+                    .sampler(sampler.build(seed, texture_size))
+                    .size(size.map_or(texture_size, glam::Vec3::from_array))
+                    .build(),
+            ),
+            VectorSamplerRecipe::FromScalars { x, y, z } => Box::new(
+                VectorFromScalarsSampler::builder()
+                    .x(x.build(seed, texture_size))
+                    .y(y.build(seed, texture_size))
+                    .z(z.build(seed, texture_size))
+                    .build(),
+            ),
+            VectorSamplerRecipe::GradientCurl { potential } => Box::new(
+                GradientCurlSampler::builder()
+                    .potential(potential.build(seed))
+                    .build(),
+            ),
+        }
+    }
+
+    /// See [`SamplerRecipe::check_tiling`].
+    fn check_tiling(&mut self, check: &mut TilingCheck, scales: &[f32]) {
+        match self {
+            VectorSamplerRecipe::VectorField { frequency, period, .. } => {
+                check.lattice("vector_field", frequency, *period, scales)
+            }
+            VectorSamplerRecipe::VectorFieldFbm {
+                sampler,
+                octaves,
+                lacunarity,
+                ..
+            } => {
+                check.multiple("vector_field_fbm lacunarity", lacunarity);
+                sampler.check_tiling(check, &octave_scales(scales, *octaves, *lacunarity));
+            }
+            VectorSamplerRecipe::Curl { sampler, .. } => sampler.check_tiling(check, scales),
+            VectorSamplerRecipe::FromScalars { x, y, z } => {
+                x.check_tiling(check, scales);
+                y.check_tiling(check, scales);
+                z.check_tiling(check, scales);
+            }
+            VectorSamplerRecipe::GradientCurl { potential } => potential.check_tiling(check, scales),
+        }
+    }
+
+    /// See [`SamplerRecipe::timeless_samplers`].
+    fn timeless_samplers(&self, dimensions: SampleDimensions, depth: u32, names: &mut Vec<&'static str>) {
+        match self {
+            VectorSamplerRecipe::VectorField { .. } | VectorSamplerRecipe::GradientCurl { .. } => {}
+            VectorSamplerRecipe::VectorFieldFbm { sampler, .. } | VectorSamplerRecipe::Curl { sampler, .. } => {
+                sampler.timeless_samplers(dimensions, depth, names)
+            }
+            VectorSamplerRecipe::FromScalars { x, y, z } => {
+                x.timeless_samplers(dimensions, depth, names);
+                y.timeless_samplers(dimensions, depth, names);
+                z.timeless_samplers(dimensions, depth, names);
+            }
+        }
+    }
+}
+
+/// A scalar sampler with analytic gradients, for [`VectorSamplerRecipe::GradientCurl`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PotentialRecipe {
+    Perlin {
+        frequency: f32,
+        #[serde(default)]
+        seed: u32,
+        period: Option<u32>,
+        #[serde(default)]
+        smoothing: Smoothing,
+        #[serde(default)]
+        mode: PerlinMode,
+        #[serde(default)]
+        vary_gradient_magnitudes: bool,
+    },
+    Fbm {
+        sampler: Box<PotentialRecipe>,
+        octaves: f32,
+        #[serde(default = "default_two")]
+        decay: f32,
+        #[serde(default = "default_two")]
+        lacunarity: f32,
+        #[serde(default)]
+        smoothing: Smoothing,
+        #[serde(default)]
+        mode: FbmMode,
+        #[serde(default = "default_one")]
+        offset: f32,
+        #[serde(default = "default_two")]
+        gain: f32,
+        /// Amplitudes of the first octaves, in place of `decay`.
+        amplitudes: Option<Vec<f32>>,
+        #[serde(default)]
+        rotate_octaves: bool,
+        #[serde(default)]
+        offset_octaves: bool,
+        min_frequency: Option<f32>,
+        max_frequency: Option<f32>,
+    },
+}
+impl PotentialRecipe {
+    pub fn build(&self, seed: u32) -> Box<dyn GradientSampler> {
+        match self {
+            PotentialRecipe::Perlin {
+                frequency,
+                seed: seed_offset,
+                period,
+                smoothing,
+                mode,
+                vary_gradient_magnitudes,
+            } => Box::new(
+                PerlinSampler::builder()
+                    .frequency(*frequency)
+                    .seed(seed.wrapping_add(*seed_offset))
+                    .maybe_period(*period)
+                    .smoothing(*smoothing)
+                    .mode(*mode)
+                    .vary_gradient_magnitudes(*vary_gradient_magnitudes)
+                    .build(),
+            ),
+            PotentialRecipe::Fbm {
+                sampler,
+                octaves,
+                decay,
+                lacunarity,
+                smoothing,
+                mode,
+                offset,
+                gain,
+                amplitudes,
+                rotate_octaves,
+                offset_octaves,
+                min_frequency,
+                max_frequency,
+            } => Box::new(
+                FbmSampler::builder()
+                    .sampler(sampler.build(seed))
+                    .octaves(*octaves)
+                    .decay(*decay)
+                    .lacunarity(*lacunarity)
+                    .smoothing(*smoothing)
+                    .mode(*mode)
+                    .offset(*offset)
+                    .gain(*gain)
+                    .maybe_amplitudes(amplitudes.clone())
+                    .rotate_octaves(*rotate_octaves)
+                    .offset_octaves(*offset_octaves)
+                    .maybe_min_frequency(*min_frequency)
+                    .maybe_max_frequency(*max_frequency)
+                    .build(),
+            ),
+        }
+    }
+
+    /// See [`SamplerRecipe::check_tiling`].
+    fn check_tiling(&mut self, check: &mut TilingCheck, scales: &[f32]) {
+        match self {
+            PotentialRecipe::Perlin { frequency, period, .. } => check.lattice("perlin", frequency, *period, scales),
+            PotentialRecipe::Fbm {
+                sampler,
+                octaves,
+                lacunarity,
+                ..
+            } => {
+                check.multiple("fbm lacunarity", lacunarity);
+                // Rotated octaves are rounded to multiples of the frequency over the period, which tile
+                // whenever the unrotated ones do.
+                sampler.check_tiling(check, &octave_scales(scales, octaves.ceil() as u32, *lacunarity));
+            }
+        }
+    }
+}
+
+/// Collects the frequencies in a sampler tree that keep the texture from tiling, rounding them to
+/// whole numbers when `round` is set.
+struct TilingCheck {
+    round: bool,
+    issues: Vec<String>,
+}
+impl TilingCheck {
+    /// A lattice sampler tiles when its period fits a whole number of times across the texture, at
+    /// the frequency of every octave in `scales`.
+    fn lattice(&mut self, name: &str, frequency: &mut f32, period: Option<u32>, scales: &[f32]) {
+        let tiles = |scale: f32| match period {
+            Some(period) => is_whole(period as f32 * scale) && is_whole(*frequency / period as f32),
+            None => is_whole(*frequency * scale),
+        };
+        let Some(scale) = scales.iter().copied().find(|scale| !tiles(*scale)) else {
+            return;
+        };
+
+        let mut issue = match period {
+            Some(period) => format!("{name} frequency {frequency} with period {period}"),
+            None => format!("{name} frequency {frequency}"),
+        };
+        if scale != 1.0 {
+            issue += &format!(" at octave frequency {}", *frequency * scale);
+        }
+        issue += " doesn't tile";
+
+        if self.round {
+            *frequency = match period {
+                Some(period) => (*frequency / period as f32).round().max(1.0) * period as f32,
+                None => frequency.round().max(1.0),
+            };
+            issue += &format!(", rounded to {frequency}");
+        }
+        self.issues.push(issue);
+    }
+
+    /// Lacunarities and frequency multiples are rounded along with the frequencies they scale, as
+    /// only whole numbers keep every octave tiling. Otherwise [`TilingCheck::lattice`] reports the
+    /// octaves they break.
+    fn multiple(&mut self, name: &str, value: &mut f32) {
+        if self.round && !is_whole(*value) {
+            let rounded = value.round().max(1.0);
+            self.issues
+                .push(format!("{name} {value} isn't a whole number, rounded to {rounded}"));
+            *value = rounded;
+        }
+    }
+}
+
+fn is_whole(value: f32) -> bool {
+    value.round() >= 1.0 && (value - value.round()).abs() <= 1e-4 * value.abs()
+}
+
+/// `scales` multiplied by the frequency of each of `octaves` octaves, `lacunarity` apart.
+fn octave_scales(scales: &[f32], octaves: u32, lacunarity: f32) -> Vec<f32> {
+    scales
+        .iter()
+        .flat_map(|scale| {
+            std::iter::successors(Some(*scale), move |scale| Some(scale * lacunarity)).take(octaves as usize)
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum RecipeError {
+    Io(PathBuf, std::io::Error),
+    Toml(PathBuf, toml::de::Error),
+    Ron(PathBuf, Box<ron::error::SpannedError>),
+    UnknownExtension(PathBuf),
+    Save(PathBuf, image::ImageError),
+    Serialize(toml::ser::Error),
+}
+impl fmt::Display for RecipeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecipeError::Io(path, err) => write!(f, "failed to read {}: {err}", path.display()),
+            RecipeError::Toml(path, err) => write!(f, "failed to parse {}: {err}", path.display()),
+            RecipeError::Ron(path, err) => write!(f, "failed to parse {}: {err}", path.display()),
+            RecipeError::UnknownExtension(path) => {
+                write!(f, "{} is neither a .toml nor a .ron recipe", path.display())
+            }
+            RecipeError::Save(path, err) => write!(f, "failed to save {}: {err}", path.display()),
+            RecipeError::Serialize(err) => write!(f, "failed to serialize the recipe: {err}"),
+        }
+    }
+}
+impl std::error::Error for RecipeError {}
+
+impl Recipe {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RecipeError> {
+        let path = path.as_ref();
+        let source =
+            std::fs::read_to_string(path).map_err(|err| RecipeError::Io(path.to_owned(), err))?;
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => {
+                toml::from_str(&source).map_err(|err| RecipeError::Toml(path.to_owned(), err))
+            }
+            Some("ron") => {
+                ron::from_str(&source).map_err(|err| RecipeError::Ron(path.to_owned(), Box::new(err)))
+            }
+            _ => Err(RecipeError::UnknownExtension(path.to_owned())),
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Result<Self, RecipeError>> {
+        let (_, source) = PRESETS.iter().find(|(preset, _)| *preset == name)?;
+
+        Some(toml::from_str(source).map_err(|err| RecipeError::Toml(PathBuf::from(name), err)))
+    }
+
+    /// Loads a recipe from a path, falling back to the bundled preset of that name.
+    pub fn load_or_preset(name: &str) -> Result<Self, RecipeError> {
+        if Path::new(name).exists() {
+            return Self::load(name);
+        }
+
+        Self::preset(name).unwrap_or_else(|| Self::load(name))
+    }
+
+    pub fn output_path<P: AsRef<Path>>(&self, output_dir: P, index: u32) -> PathBuf {
+        output_dir
+            .as_ref()
+            .join(self.output.path.replace("{index}", &index.to_string()))
+    }
+
+    /// The output path of one frame of an animation and one face of a cubemap, where there are
+    /// several. `{frame}` and `{face}` are replaced by the frame index and face name, or added
+    /// before the extension if the path has no such placeholder.
+    pub fn layer_output_path<P: AsRef<Path>>(
+        &self,
+        output_dir: P,
+        index: u32,
+        frame: Option<u32>,
+        face: Option<CubeFace>,
+    ) -> PathBuf {
+        let mut path = self.output.path.replace("{index}", &index.to_string());
+        if let Some(frame) = frame {
+            path = replace_placeholder(&path, "{frame}", &frame.to_string());
+        }
+        if let Some(face) = face {
+            path = replace_placeholder(&path, "{face}", face.name());
+        }
+
+        output_dir.as_ref().join(path)
+    }
+
+    pub fn seed_for_index(&self, index: u32, seed: u32) -> u32 {
+        if self.seed_per_index {
+            seed.wrapping_add(index)
+        } else {
+            seed
+        }
+    }
+
+    /// Frequencies that keep the textures from tiling, as warnings.
+    pub fn tiling_issues(&self) -> Vec<String> {
+        self.clone().check_tiling(false)
+    }
+
+    /// Rounds the frequencies and lacunarities of channels that wouldn't tile to whole numbers,
+    /// returning what was changed.
+    pub fn round_for_tiling(&mut self) -> Vec<String> {
+        self.check_tiling(true)
+    }
+
+    /// Samplers that stay the same in every frame of an animation, as warnings.
+    pub fn animation_issues(&self) -> Vec<String> {
+        if self.animation.is_none() {
+            return Vec::new();
+        }
+
+        let mut issues = Vec::new();
+        for (name, channel) in self.channels.iter() {
+            // Every surface but the planar volume is sampled in 3D.
+            let dimensions = match self.projection {
+                Projection::Planar => channel.dimensions,
+                _ => SampleDimensions::Three,
+            };
+
+            let mut names = Vec::new();
+            channel.sampler.timeless_samplers(dimensions, self.depth, &mut names);
+            names.sort_unstable();
+            names.dedup();
+
+            issues.extend(names.into_iter().map(|sampler| {
+                format!("channel {name}: {sampler} has no time axis, so it is the same in every frame")
+            }));
+        }
+
+        issues
+    }
+
+    fn check_tiling(&mut self, round: bool) -> Vec<String> {
+        // The sphere is sampled away from the borders of the volume, so it doesn't need to tile.
+        if !matches!(self.projection, Projection::Planar) {
+            return Vec::new();
+        }
+
+        let mut issues = Vec::new();
+        for (name, channel) in self.channels.iter_mut() {
+            let mut check = TilingCheck {
+                round: false,
+                issues: Vec::new(),
+            };
+            channel.sampler.check_tiling(&mut check, &[1.0]);
+
+            if round && !check.issues.is_empty() {
+                check = TilingCheck {
+                    round: true,
+                    issues: Vec::new(),
+                };
+                channel.sampler.check_tiling(&mut check, &[1.0]);
+            }
+
+            issues.extend(check.issues.into_iter().map(|issue| format!("channel {name}: {issue}")));
+        }
+
+        issues
+    }
+
+    /// Generates the texture at `index`, or every frame of it for an animation, returning the
+    /// written paths.
+    pub fn generate<P: AsRef<Path>>(
+        &self,
+        output_dir: P,
+        index: u32,
+        seed: u32,
+    ) -> Result<Vec<PathBuf>, RecipeError> {
+        let generated = self.generate_checked(output_dir, index, seed, false)?;
+
+        Ok(generated.into_iter().flat_map(|texture| texture.paths).collect())
+    }
+
+    /// Like [`Recipe::generate`], grouping the paths by texture and also comparing opposite borders
+    /// of each texture if `check_seams` is set.
+    pub fn generate_checked<P: AsRef<Path>>(
+        &self,
+        output_dir: P,
+        index: u32,
+        seed: u32,
+        check_seams: bool,
+    ) -> Result<Vec<GeneratedTexture>, RecipeError> {
+        let output_dir = output_dir.as_ref();
+        let seed = self.seed_for_index(index, seed);
+
+        match self.format {
+            PixelFormat::Rgba8 => self.render_and_save::<Rgba8>(output_dir, index, seed, check_seams),
+            PixelFormat::Rgb8 => self.render_and_save::<Rgb8>(output_dir, index, seed, check_seams),
+            PixelFormat::Rg8 => self.render_and_save::<Rg8>(output_dir, index, seed, check_seams),
+            PixelFormat::R8 => self.render_and_save::<R8>(output_dir, index, seed, check_seams),
+            PixelFormat::Rgba16 => self.render_and_save::<Rgba16>(output_dir, index, seed, check_seams),
+            PixelFormat::Rgb16 => self.render_and_save::<Rgb16>(output_dir, index, seed, check_seams),
+            PixelFormat::Rg16 => self.render_and_save::<Rg16>(output_dir, index, seed, check_seams),
+            PixelFormat::R16 => self.render_and_save::<R16>(output_dir, index, seed, check_seams),
+            PixelFormat::R16f => self.render_and_save::<R16f>(output_dir, index, seed, check_seams),
+            PixelFormat::Rg16f => self.render_and_save::<Rg16f>(output_dir, index, seed, check_seams),
+            PixelFormat::Rgba16f => {
+                self.render_and_save::<Rgba16f>(output_dir, index, seed, check_seams)
+            }
+            PixelFormat::R32f => self.render_and_save::<R32f>(output_dir, index, seed, check_seams),
+        }
+    }
+
+    /// Renders a single z-slice of the volume, at `frame` of an animation, to a PNG, normalized on
+    /// its own.
+    pub fn preview<P: AsRef<Path>>(
+        &self,
+        path: P,
+        slice: u32,
+        frame: u32,
+        seed: u32,
+    ) -> Result<(), RecipeError> {
+        let path = path.as_ref();
+        let surface = self.projection.preview_surface();
+        let time = self.frame_time(frame);
+        let result = match self.format {
+            PixelFormat::Rgba8 => self
+                .render_surface::<Rgba8>(seed, surface, slice, 1, time)
+                .save_as_image(path),
+            PixelFormat::Rgb8 => self
+                .render_surface::<Rgb8>(seed, surface, slice, 1, time)
+                .save_as_image(path),
+            PixelFormat::Rg8 => self
+                .render_surface::<Rg8>(seed, surface, slice, 1, time)
+                .save_as_image(path),
+            PixelFormat::R8 => self
+                .render_surface::<R8>(seed, surface, slice, 1, time)
+                .save_as_image(path),
+            PixelFormat::Rgba16 => self
+                .render_surface::<Rgba16>(seed, surface, slice, 1, time)
+                .save_as_image(path),
+            PixelFormat::Rgb16 => self
+                .render_surface::<Rgb16>(seed, surface, slice, 1, time)
+                .save_as_image(path),
+            PixelFormat::Rg16 => self
+                .render_surface::<Rg16>(seed, surface, slice, 1, time)
+                .save_as_image(path),
+            PixelFormat::R16 => self
+                .render_surface::<R16>(seed, surface, slice, 1, time)
+                .save_as_image(path),
+            PixelFormat::R16f => self
+                .render_surface::<R16f>(seed, surface, slice, 1, time)
+                .save_as_image(path),
+            PixelFormat::Rg16f => self
+                .render_surface::<Rg16f>(seed, surface, slice, 1, time)
+                .save_as_image(path),
+            PixelFormat::Rgba16f => self
+                .render_surface::<Rgba16f>(seed, surface, slice, 1, time)
+                .save_as_image(path),
+            PixelFormat::R32f => self
+                .render_surface::<R32f>(seed, surface, slice, 1, time)
+                .save_as_image(path),
+        };
+
+        result.map_err(|err| RecipeError::Save(path.to_owned(), err))
+    }
+
+    /// Renders the texture, cubemaps as a horizontal cross.
+    pub fn render<P: PixelType>(&self, seed: u32) -> Noisetex<P> {
+        self.render_surface(seed, self.projection.preview_surface(), 0, self.depth, None)
+    }
+
+    /// Renders every frame of the animation, one texture if there is none, for each surface of the
+    /// projection.
+    ///
+    /// Everything is normalized together, so frames don't flicker and cubemap faces meet without
+    /// seams. The samplers are built once and shared by every frame and surface, so patterns
+    /// generated on first use, like blue noise, are only generated once.
+    pub fn render_frames<P: PixelType>(&self, seed: u32) -> Vec<(Surface, Vec<Noisetex<P>>)> {
+        let norms: Vec<Normalized> = (0..P::CHANNELS).map(|_| Normalized::new()).collect();
+        let channels = self.build_channels(seed, P::CHANNELS);
+
+        let mut surfaces: Vec<(Surface, Vec<Noisetex<P>>)> = self
+            .projection
+            .surfaces()
+            .into_iter()
+            .map(|surface| {
+                let frames = (0..self.frame_count())
+                    .map(|frame| {
+                        let time = self.frame_time(frame);
+                        self.sample_slices(&channels, surface, 0, self.depth, time, &norms)
+                    })
+                    .collect();
+                (surface, frames)
+            })
+            .collect();
+        for noisetex in surfaces.iter_mut().flat_map(|(_, frames)| frames) {
+            self.normalize(noisetex, &norms);
+        }
+
+        surfaces
+    }
+
+    /// Renders `count` z-slices starting at `first`, placed where they would be in the full volume,
+    /// at `time` if animated.
+    pub fn render_slices<P: PixelType>(
+        &self,
+        seed: u32,
+        first: u32,
+        count: u32,
+        time: Option<f32>,
+    ) -> Noisetex<P> {
+        self.render_surface(seed, Surface::Volume, first, count, time)
+    }
+
+    /// Renders one surface of the projection on its own, `first` and `count` picking the z-slices
+    /// of a planar volume.
+    fn render_surface<P: PixelType>(
+        &self,
+        seed: u32,
+        surface: Surface,
+        first: u32,
+        count: u32,
+        time: Option<f32>,
+    ) -> Noisetex<P> {
+        let norms: Vec<Normalized> = (0..P::CHANNELS).map(|_| Normalized::new()).collect();
+        let channels = self.build_channels(seed, P::CHANNELS);
+
+        let mut noisetex = self.sample_slices(&channels, surface, first, count, time, &norms);
+        self.normalize(&mut noisetex, &norms);
+
+        noisetex
+    }
+
+    /// Frames in the animation, 1 without one.
+    pub fn frame_count(&self) -> u32 {
+        self.animation.as_ref().map_or(1, |animation| animation.frames.max(1))
+    }
+
+    /// Where `frame` falls in the loop, evenly spaced so the last frame leads back into the first.
+    fn frame_time(&self, frame: u32) -> Option<f32> {
+        self.animation
+            .as_ref()
+            .map(|_| frame as f32 / self.frame_count() as f32)
+    }
+
+    /// Builds the samplers of the first `count` channels.
+    fn build_channels(&self, seed: u32, count: usize) -> Vec<Option<BuiltChannel<'_>>> {
+        let size = glam::uvec3(self.width, self.height, self.depth).as_vec3();
+
+        (0..count)
+            .map(|index| self.channels.get(index).map(|channel| channel.build(seed, size)))
+            .collect()
+    }
+
+    /// Fills a texture with the raw channel values, widening `norms` to their range.
+    ///
+    /// Spherical surfaces sample the sphere inscribed in the texture volume, and leave pixels that
+    /// don't map onto it alone.
+    fn sample_slices<P: PixelType>(
+        &self,
+        channels: &[Option<BuiltChannel>],
+        surface: Surface,
+        first: u32,
+        count: u32,
+        time: Option<f32>,
+        norms: &[Normalized],
+    ) -> Noisetex<P> {
+        let surface_size = surface.size(self.width, self.height, count);
+        let mut noisetex = Noisetex::<P>::new(surface_size.x, surface_size.y, surface_size.z);
+        let size = glam::uvec3(self.width, self.height, self.depth).as_vec3();
+
+        noisetex.fill(|_info, pixel, pos| {
+            let (uvw, dimensions) = if surface == Surface::Volume {
+                ((pos + glam::UVec3::Z * first).as_vec3() / size, None)
+            } else {
+                let face_size = glam::uvec2(self.width, self.height);
+                match surface.direction(pos.xy(), face_size) {
+                    Some(direction) => (direction * 0.5 + 0.5, Some(SampleDimensions::Three)),
+                    None => return,
+                }
+            };
+
+            for (index, (channel, norm)) in channels.iter().zip(norms).enumerate() {
+                if let Some(channel) = channel {
+                    let value = channel.sample(uvw, dimensions, time);
+                    pixel.set_channel(index, norm.update_with(value));
+                }
+            }
+        });
+
+        noisetex
+    }
+
+    fn normalize<P: PixelType>(&self, noisetex: &mut Noisetex<P>, norms: &[Normalized]) {
+        noisetex.fill(|_info, pixel, _pos| {
+            for (index, norm) in norms.iter().enumerate() {
+                if self
+                    .channels
+                    .get(index)
+                    .is_some_and(|channel| channel.normalizes(self.format))
+                {
+                    pixel.set_channel(index, pixel.channel(index).map(norm.min(), norm.max()));
+                }
+            }
+        });
+    }
+
+    fn render_and_save<P: PixelType>(
+        &self,
+        output_dir: &Path,
+        index: u32,
+        seed: u32,
+        check_seams: bool,
+    ) -> Result<Vec<GeneratedTexture>, RecipeError> {
+        let mut saved = Vec::new();
+        for (surface, mut frames) in self.render_frames::<P>(seed) {
+            if self.animation.as_ref().is_some_and(|animation| animation.stack) {
+                frames = vec![Noisetex::stack(frames)];
+            }
+
+            let animated = frames.len() > 1;
+            for (frame, noisetex) in frames.iter().enumerate() {
+                let frame = animated.then_some(frame as u32);
+                let path = self.layer_output_path(output_dir, index, frame, surface.face());
+                let paths = self.save(&path, noisetex, index, seed)?;
+
+                saved.push(GeneratedTexture {
+                    paths,
+                    seams: check_seams.then(|| noisetex.seam_check()),
+                });
+            }
+        }
+
+        Ok(saved)
+    }
+
+    /// Saves a texture as the output kind asks, returning the written paths.
+    fn save<P: PixelType>(
+        &self,
+        path: &Path,
+        noisetex: &Noisetex<P>,
+        index: u32,
+        seed: u32,
+    ) -> Result<Vec<PathBuf>, RecipeError> {
+        let container = match self.output.kind {
+            OutputKind::Binary => Some(noisetex.save_as_binary(path, self.output.endianness)),
+            OutputKind::Container => {
+                let provenance = Provenance {
+                    seed,
+                    index,
+                    recipe: toml::to_string(self).map_err(RecipeError::Serialize)?,
+                };
+                Some(noisetex.save_as_container(path, self.output.endianness, provenance))
+            }
+            OutputKind::Dds => Some(noisetex.save_as_dds(path, self.output.layout)),
+            OutputKind::Ktx2 => Some(noisetex.save_as_ktx2(path, self.output.layout)),
+            _ => None,
+        };
+        if let Some(result) = container {
+            result.map_err(|err| RecipeError::Save(path.to_owned(), image::ImageError::from(err)))?;
+            return Ok(vec![path.to_owned()]);
+        }
+        if let OutputKind::Exr = self.output.kind {
+            noisetex
+                .save_as_exr(path)
+                .map_err(|err| RecipeError::Save(path.to_owned(), err))?;
+            return Ok(vec![path.to_owned()]);
+        }
+
+        let depth = noisetex.info().size().z;
+        let path = path.to_string_lossy();
+        let mut saved = Vec::new();
+
+        if let OutputKind::Image | OutputKind::ImageAndSlices = self.output.kind {
+            let atlas = PathBuf::from(path.replace("{slice}", ""));
+            let columns = self
+                .output
+                .columns
+                .unwrap_or_else(|| Noisetex::<P>::default_atlas_columns(depth));
+
+            noisetex
+                .save_as_atlas(&atlas, columns)
+                .map_err(|err| RecipeError::Save(atlas.clone(), err))?;
+            saved.push(atlas);
+        }
+        if let OutputKind::Slices | OutputKind::ImageAndSlices = self.output.kind {
+            for slice in 0..depth {
+                let slice_path =
+                    PathBuf::from(replace_placeholder(&path, "{slice}", &slice.to_string()));
+
+                noisetex
+                    .save_slice_as_image(&slice_path, slice)
+                    .map_err(|err| RecipeError::Save(slice_path.clone(), err))?;
+                saved.push(slice_path);
+            }
+        }
+
+        Ok(saved)
+    }
+}
+
+impl ChannelRecipe {
+    fn build(&self, seed: u32, texture_size: glam::Vec3) -> BuiltChannel<'_> {
+        BuiltChannel {
+            recipe: self,
+            sampler: self.sampler.build(seed, texture_size),
+        }
+    }
+}
+
+/// A channel with its sampler tree built once, shared by every pixel, frame and surface of a render.
+struct BuiltChannel<'a> {
+    recipe: &'a ChannelRecipe,
+    sampler: Box<dyn NoiseSampler<f32>>,
+}
+impl BuiltChannel<'_> {
+    /// Samples the channel at `uvw`, in `dimensions` if given instead of those of the channel.
+    fn sample(
+        &self,
+        uvw: glam::Vec3,
+        dimensions: Option<SampleDimensions>,
+        time: Option<f32>,
+    ) -> f32 {
+        let value = match (dimensions.unwrap_or(self.recipe.dimensions), time) {
+            (SampleDimensions::Two, None) => self.sampler.sample_2d(uvw.xy()),
+            (SampleDimensions::Three, None) => self.sampler.sample_3d(uvw),
+            (SampleDimensions::Two, Some(time)) => {
+                self.sampler.sample_2d_time(uvw.xy().extend(time))
+            }
+            (SampleDimensions::Three, Some(time)) => self.sampler.sample_4d(uvw.extend(time)),
+        };
+
+        self.recipe
+            .post
+            .iter()
+            .fold(value, |value, post| post.apply(value))
+    }
+}
+
+/// Replaces `placeholder` in an output path by `value`, or adds `_value` before the extension if
+/// there's no such placeholder.
+fn replace_placeholder(path: &str, placeholder: &str, value: &str) -> String {
+    if path.contains(placeholder) {
+        return path.replace(placeholder, value);
+    }
+
+    let name_start = path.rfind('/').map_or(0, |slash| slash + 1);
+    let extension_start = path[name_start..]
+        .rfind('.')
+        .map_or(path.len(), |dot| name_start + dot);
+
+    format!("{}_{value}{}", &path[..extension_start], &path[extension_start..])
+}
+
+fn default_depth() -> u32 {
+    1
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_one() -> f32 {
+    1.0
+}
+
+fn default_perlin_octaves() -> u32 {
+    3
+}
+
+fn default_worley_frequencies() -> [f32; 3] {
+    [1.0, 2.0, 4.0]
+}
+
+fn default_one_u32() -> u32 {
+    1
+}
+
+fn default_two() -> f32 {
+    2.0
+}
+
+fn default_blue_noise_sigma() -> f32 {
+    1.5
+}
+
+fn default_gabor_bandwidth() -> f32 {
+    1.5
+}
+
+fn default_impulses_per_cell() -> f32 {
+    8.0
+}
@@ -1,26 +1,34 @@
 use bon::Builder;
-use glam::{Vec3Swizzles, Vec4Swizzles};
 
-use crate::random::hash::{pcg_33, pcg_44};
-use crate::samplers::{NoiseSampler, NoiseSamplerState, Smoothing};
+use crate::samplers::cell::{cell_points_2d, cell_points_3d};
+use crate::samplers::{NoiseSampler, NoiseSamplerState, SampleParams, Smoothing};
 use crate::util::SmoothingFn;
 
+/// Layered bumps around random points in each lattice cell, the strongest minus the second strongest.
+///
+/// `jitter` and `points_per_cell` place the points like they do for
+/// [`WorleySampler`](crate::samplers::worley::WorleySampler).
 #[derive(Debug, Builder)]
 pub struct AlligatorSampler {
     pub frequency: f32,
     #[builder(default = 0)]
     pub seed: u32,
+    /// Lattice cells the noise repeats after at `frequency`, the frequency itself if unset.
+    pub period: Option<u32>,
     #[builder(default = Smoothing::None)]
     pub smoothing: Smoothing,
     #[builder(default = true)]
-    pub randomize_cell_strength: bool
+    pub randomize_cell_strength: bool,
+    #[builder(default = 1.0)]
+    pub jitter: f32,
+    pub points_per_cell: Option<f32>,
 }
 impl NoiseSampler<f32> for AlligatorSampler {
-    fn sample_2d(&mut self, mut uv: glam::Vec2) -> f32 {
-        uv *= glam::Vec2::splat(self.frequency);
+    fn sample_2d_with(&self, mut uv: glam::Vec2, params: SampleParams) -> f32 {
+        uv *= glam::Vec2::splat(params.frequency);
 
         let p = uv.floor().as_ivec2();
-        let f = uv.fract();
+        let f = uv.fract_gl();
 
         let mut smallest_strength: f32 = 0.0;
         let mut second_smallest_strength: f32 = 0.0;
@@ -28,23 +36,24 @@ impl NoiseSampler<f32> for AlligatorSampler {
         for x in -1..=1 {
             for y in -1..=1 {
                 let offset = glam::IVec2::new(x, y);
-                let cell = (p + offset).rem_euclid(glam::IVec2::splat(self.frequency as i32));
-
-                let hash3 = pcg_33(glam::UVec3::new(cell.x.cast_unsigned(), cell.y.cast_unsigned(), self.seed));
-                let point = (hash3.xy().as_vec2() / (u32::MAX as f32)) + offset.as_vec2();
-
-                let strength = if self.randomize_cell_strength {
-                    (hash3.z as f32) / (u32::MAX as f32)
-                } else {
-                    1.0
-                };
-
-                let dist = (1.0 - f.distance(point)).cubic_smooth() * strength;
-                if dist > smallest_strength {
-                    second_smallest_strength = smallest_strength;
-                    smallest_strength = dist;
-                } else if dist > second_smallest_strength {
-                    second_smallest_strength = dist
+                let cell = (p + offset).rem_euclid(glam::IVec2::splat(params.period(self.period, self.frequency)));
+
+                for (point, hash) in cell_points_2d(cell.as_uvec2(), params.seed, self.jitter, self.points_per_cell) {
+                    let point = point + offset.as_vec2();
+
+                    let strength = if self.randomize_cell_strength {
+                        (hash as f32) / (u32::MAX as f32)
+                    } else {
+                        1.0
+                    };
+
+                    let dist = (1.0 - f.distance(point)).cubic_smooth() * strength;
+                    if dist > smallest_strength {
+                        second_smallest_strength = smallest_strength;
+                        smallest_strength = dist;
+                    } else if dist > second_smallest_strength {
+                        second_smallest_strength = dist
+                    }
                 }
             }
         }
@@ -55,11 +64,11 @@ impl NoiseSampler<f32> for AlligatorSampler {
         self.smoothing.smooth(smallest_strength - second_smallest_strength)
     }
     
-    fn sample_3d(&mut self, mut uvw: glam::Vec3) -> f32 {
-        uvw *= glam::Vec3::splat(self.frequency);
+    fn sample_3d_with(&self, mut uvw: glam::Vec3, params: SampleParams) -> f32 {
+        uvw *= glam::Vec3::splat(params.frequency);
 
         let p = uvw.floor().as_ivec3();
-        let f = uvw.fract();
+        let f = uvw.fract_gl();
 
         let mut smallest_dist: f32 = 0.0;
         let mut second_smallest_dist: f32 = 0.0;
@@ -68,23 +77,24 @@ impl NoiseSampler<f32> for AlligatorSampler {
             for y in -1..=1 {
                 for z in -1..=1 {
                     let offset = glam::IVec3::new(x, y, z);
-                    let cell = (p + offset).rem_euclid(glam::IVec3::splat(self.frequency as i32));
-
-                    let hash4 = pcg_44(glam::UVec4::new(cell.x.cast_unsigned(), cell.y.cast_unsigned(), cell.z.cast_unsigned(), self.seed));
-                    let point = (hash4.xyz().as_vec3() / (u32::MAX as f32)) + offset.as_vec3();
-
-                    let strength = if self.randomize_cell_strength {
-                        (hash4.w as f32) / (u32::MAX as f32)
-                    } else {
-                        1.0
-                    };
-
-                    let dist = (1.0 - f.distance(point)).quintic_smooth() * strength;
-                    if dist > smallest_dist {
-                        second_smallest_dist = smallest_dist;
-                        smallest_dist = dist;
-                    } else if dist > second_smallest_dist {
-                        second_smallest_dist = dist
+                    let cell = (p + offset).rem_euclid(glam::IVec3::splat(params.period(self.period, self.frequency)));
+
+                    for (point, hash) in cell_points_3d(cell.as_uvec3(), params.seed, self.jitter, self.points_per_cell) {
+                        let point = point + offset.as_vec3();
+
+                        let strength = if self.randomize_cell_strength {
+                            (hash as f32) / (u32::MAX as f32)
+                        } else {
+                            1.0
+                        };
+
+                        let dist = (1.0 - f.distance(point)).quintic_smooth() * strength;
+                        if dist > smallest_dist {
+                            second_smallest_dist = smallest_dist;
+                            smallest_dist = dist;
+                        } else if dist > second_smallest_dist {
+                            second_smallest_dist = dist
+                        }
                     }
                 }
             }
@@ -105,6 +115,10 @@ impl NoiseSamplerState for AlligatorSampler {
         self.seed
     }
 
+    fn get_period(&self) -> Option<u32> {
+        self.period
+    }
+
     fn set_frequency(&mut self, new_frequency: f32) {
         self.frequency = new_frequency;
     }
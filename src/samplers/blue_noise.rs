@@ -0,0 +1,379 @@
+use std::sync::OnceLock;
+
+use bon::Builder;
+
+use crate::random::hash::{pcg_31, pcg_33};
+use crate::samplers::{NoiseSampler, NoiseSamplerState, SampleParams};
+
+/// Share of the texels set in the initial pattern of void-and-cluster.
+const INITIAL_DENSITY: f32 = 0.1;
+
+/// Largest random offset added to the energies, so ties break randomly instead of by position.
+const ENERGY_JITTER: f32 = 1e-4;
+
+/// A tileable blue-noise pattern, generated with Ulichney's void-and-cluster method.
+///
+/// Every texel gets the rank it was picked at, divided by the texel count, so the values are
+/// uniformly distributed and any threshold of them gives evenly spread points. `sigma` is the width
+/// of the Gaussian that measures how clustered the points are, in texels.
+///
+/// The patterns are generated on first use, at `size` for 2D sampling and with the full depth for
+/// 3D. With `temporal_sigma` set, the 3D pattern is spatiotemporal: every z-slice is a 2D blue
+/// noise and every texel is a 1D blue noise along z, for jitter that changes each frame. Sampling
+/// with a seed other than its own shifts the pattern by a random amount instead of generating a new
+/// one.
+#[derive(Debug, Builder)]
+pub struct BlueNoiseSampler {
+    pub size: glam::UVec3,
+    /// Times the pattern repeats across the texture.
+    #[builder(default = 1.0)]
+    pub frequency: f32,
+    #[builder(default = 0)]
+    pub seed: u32,
+    #[builder(default = 1.5)]
+    pub sigma: f32,
+    pub temporal_sigma: Option<f32>,
+    #[builder(skip)]
+    planar: OnceLock<Vec<f32>>,
+    #[builder(skip)]
+    volume: OnceLock<Vec<f32>>,
+}
+impl BlueNoiseSampler {
+    fn lookup(&self, pattern: &[f32], size: glam::UVec3, uvw: glam::Vec3, params: SampleParams) -> f32 {
+        let size = size.as_ivec3();
+        let shift = pcg_33(glam::uvec3(params.seed.wrapping_sub(self.seed), 0, 0)).as_ivec3();
+
+        let texel = (uvw * size.as_vec3() * params.frequency).round().as_ivec3();
+        let texel = (texel + shift.rem_euclid(size)).rem_euclid(size);
+
+        pattern[(texel.x + size.x * (texel.y + size.y * texel.z)) as usize]
+    }
+
+    fn planar_size(&self) -> glam::UVec3 {
+        self.size.with_z(1)
+    }
+}
+impl NoiseSampler<f32> for BlueNoiseSampler {
+    fn sample_2d_with(&self, uv: glam::Vec2, params: SampleParams) -> f32 {
+        let pattern = self.planar.get_or_init(|| {
+            let kernel = energy_kernel(self.planar_size(), self.sigma, None);
+            void_and_cluster(self.planar_size(), &kernel, self.seed)
+        });
+
+        self.lookup(pattern, self.planar_size(), uv.extend(0.0), params)
+    }
+
+    fn sample_3d_with(&self, uvw: glam::Vec3, params: SampleParams) -> f32 {
+        let pattern = self.volume.get_or_init(|| {
+            let kernel = energy_kernel(self.size, self.sigma, self.temporal_sigma);
+            void_and_cluster(self.size, &kernel, self.seed)
+        });
+
+        self.lookup(pattern, self.size, uvw, params)
+    }
+}
+impl NoiseSamplerState for BlueNoiseSampler {
+    fn get_frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    fn get_seed(&self) -> u32 {
+        self.seed
+    }
+
+    fn set_frequency(&mut self, new_frequency: f32) {
+        self.frequency = new_frequency;
+    }
+
+    fn set_seed(&mut self, new_seed: u32) {
+        self.seed = new_seed;
+        self.planar = OnceLock::new();
+        self.volume = OnceLock::new();
+    }
+}
+
+/// Texel offsets and weights of the Gaussian energy filter, cut off at three standard deviations
+/// or short of half the pattern, whichever is smaller, so no offset wraps onto another.
+///
+/// With a `temporal_sigma` the filter only reaches within the same slice and along the same texel
+/// in z, instead of over a ball.
+fn energy_kernel(size: glam::UVec3, sigma: f32, temporal_sigma: Option<f32>) -> Vec<(glam::IVec3, f32)> {
+    let reach = |sigma: f32, size: u32| ((3.0 * sigma).ceil() as i32).min((size as i32 - 1) / 2);
+    let weight = |distance_squared: f32, sigma: f32| (-distance_squared / (2.0 * sigma * sigma)).exp();
+
+    let radius = glam::ivec3(reach(sigma, size.x), reach(sigma, size.y), reach(sigma, size.z));
+    let mut kernel = Vec::new();
+
+    match temporal_sigma {
+        Some(temporal_sigma) => {
+            for x in -radius.x..=radius.x {
+                for y in -radius.y..=radius.y {
+                    let offset = glam::ivec3(x, y, 0);
+                    kernel.push((offset, weight(offset.length_squared() as f32, sigma)));
+                }
+            }
+
+            let depth = reach(temporal_sigma, size.z);
+            for z in (-depth..=depth).filter(|z| *z != 0) {
+                kernel.push((glam::ivec3(0, 0, z), weight((z * z) as f32, temporal_sigma)));
+            }
+        }
+        None => {
+            for x in -radius.x..=radius.x {
+                for y in -radius.y..=radius.y {
+                    for z in -radius.z..=radius.z {
+                        let offset = glam::ivec3(x, y, z);
+                        kernel.push((offset, weight(offset.length_squared() as f32, sigma)));
+                    }
+                }
+            }
+        }
+    }
+
+    kernel
+}
+
+/// Ranks every texel of a pattern of `size` by void-and-cluster, returning `(rank + 0.5) / count`.
+///
+/// A random initial pattern is relaxed by moving its tightest cluster into its largest void until
+/// that changes nothing. Ranks below its point count are then given by removing the tightest
+/// clusters one by one, and those above by filling the largest voids.
+fn void_and_cluster(size: glam::UVec3, kernel: &[(glam::IVec3, f32)], seed: u32) -> Vec<f32> {
+    let count = size.element_product() as usize;
+    let threshold = (INITIAL_DENSITY * u32::MAX as f32) as u32;
+
+    let mut initial: Vec<bool> = (0..count)
+        .map(|index| pcg_31(glam::uvec3(index as u32, seed, 0)) < threshold)
+        .collect();
+    if !initial.contains(&true) {
+        initial[pcg_31(glam::uvec3(seed, 0, 0)) as usize % count] = true;
+    }
+
+    let mut pattern = Pattern::new(size, kernel, seed, &initial);
+    for _ in 0..count {
+        let cluster = pattern.tightest_cluster();
+        pattern.toggle(cluster);
+
+        let void = pattern.largest_void();
+        pattern.toggle(void);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let prototype = pattern.points.clone();
+    let points = prototype.iter().filter(|point| **point).count();
+    let mut ranks = vec![0; count];
+
+    for rank in (0..points).rev() {
+        let cluster = pattern.tightest_cluster();
+        pattern.toggle(cluster);
+        ranks[cluster] = rank;
+    }
+
+    let mut pattern = Pattern::new(size, kernel, seed, &prototype);
+    for rank in points..count {
+        let void = pattern.largest_void();
+        pattern.toggle(void);
+        ranks[void] = rank;
+    }
+
+    ranks
+        .into_iter()
+        .map(|rank| (rank as f32 + 0.5) / count as f32)
+        .collect()
+}
+
+/// The smallest energy among the empty texels and the largest among the points, below a node of
+/// [`Pattern::extremes`].
+#[derive(Debug, Clone, Copy)]
+struct Extremes {
+    void: (f32, usize),
+    cluster: (f32, usize),
+}
+impl Extremes {
+    const NONE: Extremes = Extremes {
+        void: (f32::MAX, usize::MAX),
+        cluster: (f32::MIN, usize::MAX),
+    };
+
+    fn combine(a: Extremes, b: Extremes) -> Extremes {
+        Extremes {
+            void: if b.void.0 < a.void.0 { b.void } else { a.void },
+            cluster: if b.cluster.0 > a.cluster.0 { b.cluster } else { a.cluster },
+        }
+    }
+}
+
+/// A binary pattern on a torus with the filtered energy of its points at every texel.
+///
+/// The extremes are kept in a binary tree over the texels, so finding a void or cluster doesn't
+/// scan the whole pattern.
+struct Pattern<'a> {
+    size: glam::IVec3,
+    kernel: &'a [(glam::IVec3, f32)],
+    points: Vec<bool>,
+    energy: Vec<f32>,
+    extremes: Vec<Extremes>,
+    leaves: usize,
+}
+impl<'a> Pattern<'a> {
+    fn new(size: glam::UVec3, kernel: &'a [(glam::IVec3, f32)], seed: u32, points: &[bool]) -> Self {
+        let leaves = points.len().next_power_of_two();
+        let energy = (0..points.len())
+            .map(|index| pcg_31(glam::uvec3(index as u32, seed, 1)) as f32 / u32::MAX as f32 * ENERGY_JITTER)
+            .collect();
+
+        let mut pattern = Self {
+            size: size.as_ivec3(),
+            kernel,
+            points: vec![false; points.len()],
+            energy,
+            extremes: vec![Extremes::NONE; 2 * leaves],
+            leaves,
+        };
+
+        for (index, point) in points.iter().enumerate() {
+            if *point {
+                pattern.spread(index, 1.0);
+                pattern.points[index] = true;
+            }
+        }
+        for index in 0..points.len() {
+            pattern.extremes[leaves + index] = pattern.leaf(index);
+        }
+        for node in (1..leaves).rev() {
+            pattern.extremes[node] = Extremes::combine(pattern.extremes[2 * node], pattern.extremes[2 * node + 1]);
+        }
+
+        pattern
+    }
+
+    fn tightest_cluster(&self) -> usize {
+        self.extremes[1].cluster.1
+    }
+
+    fn largest_void(&self) -> usize {
+        self.extremes[1].void.1
+    }
+
+    /// Adds a point at `index` if there is none, or removes it.
+    fn toggle(&mut self, index: usize) {
+        self.points[index] = !self.points[index];
+
+        let sign = if self.points[index] { 1.0 } else { -1.0 };
+        let mut nodes = self.spread(index, sign);
+        nodes.push(index);
+
+        // Each node above the changed texels is recomputed once, however many of them it covers.
+        for node in nodes.iter_mut() {
+            self.extremes[self.leaves + *node] = self.leaf(*node);
+            *node = (self.leaves + *node) / 2;
+        }
+        while !nodes.is_empty() {
+            nodes.sort_unstable();
+            nodes.dedup();
+
+            for node in &nodes {
+                self.extremes[*node] = Extremes::combine(self.extremes[2 * node], self.extremes[2 * node + 1]);
+            }
+            nodes.retain_mut(|node| {
+                *node /= 2;
+                *node > 0
+            });
+        }
+    }
+
+    /// Adds the filtered energy of a point at `index`, scaled by `sign`, returning the texels it
+    /// reached.
+    fn spread(&mut self, index: usize, sign: f32) -> Vec<usize> {
+        let size = self.size;
+        let position = glam::ivec3(
+            index as i32 % size.x,
+            index as i32 / size.x % size.y,
+            index as i32 / (size.x * size.y),
+        );
+
+        self.kernel
+            .iter()
+            .map(|(offset, weight)| {
+                let texel = (position + *offset).rem_euclid(size);
+                let texel = (texel.x + size.x * (texel.y + size.y * texel.z)) as usize;
+
+                self.energy[texel] += sign * weight;
+                texel
+            })
+            .collect()
+    }
+
+    fn leaf(&self, index: usize) -> Extremes {
+        if self.points[index] {
+            Extremes {
+                cluster: (self.energy[index], index),
+                ..Extremes::NONE
+            }
+        } else {
+            Extremes {
+                void: (self.energy[index], index),
+                ..Extremes::NONE
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kernel_reaches_every_texel_once() {
+        for (size, temporal_sigma) in [(glam::uvec3(8, 8, 4), None), (glam::uvec3(8, 7, 6), Some(2.0))] {
+            let kernel = energy_kernel(size, 3.0, temporal_sigma);
+            let mut texels: Vec<_> = kernel
+                .iter()
+                .map(|(offset, _)| offset.rem_euclid(size.as_ivec3()).to_array())
+                .collect();
+            texels.sort_unstable();
+            texels.dedup();
+            assert_eq!(texels.len(), kernel.len(), "kernel of {size} wraps onto itself");
+        }
+    }
+
+    #[test]
+    fn ranks_are_a_permutation() {
+        for size in [glam::uvec3(16, 16, 1), glam::uvec3(8, 6, 4)] {
+            let count = size.element_product() as usize;
+            let kernel = energy_kernel(size, 1.5, None);
+            let mut ranks: Vec<_> = void_and_cluster(size, &kernel, 3)
+                .into_iter()
+                .map(|value| (value * count as f32 - 0.5).round() as usize)
+                .collect();
+            ranks.sort_unstable();
+            assert_eq!(ranks, (0..count).collect::<Vec<_>>(), "ranks of {size}");
+        }
+    }
+
+    #[test]
+    fn half_threshold_selects_half_the_texels() {
+        let sampler = BlueNoiseSampler::builder().size(glam::uvec3(16, 16, 1)).seed(5).build();
+        let size = sampler.size.as_vec3();
+
+        // Sampling with another seed only shifts the pattern, so the share stays the same.
+        for seed in [5, 6] {
+            let params = SampleParams {
+                seed,
+                ..sampler.params()
+            };
+            let mut selected = 0;
+            for y in 0..16 {
+                for x in 0..16 {
+                    let uv = glam::vec2(x as f32, y as f32) / size.truncate();
+                    if sampler.sample_2d_with(uv, params) < 0.5 {
+                        selected += 1;
+                    }
+                }
+            }
+            assert_eq!(selected, 128, "{selected} of 256 texels below 0.5 with seed {seed}");
+        }
+    }
+}
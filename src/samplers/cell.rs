@@ -0,0 +1,91 @@
+//! Feature points shared by the cellular samplers.
+
+use glam::{Vec3Swizzles, Vec4Swizzles};
+
+use crate::random::hash::{pcg_31, pcg_33, pcg_41, pcg_44, pcg_54};
+
+/// Most points a cell can get with `points_per_cell`, however unlikely.
+const MAX_POINTS_PER_CELL: u32 = 16;
+
+/// Offsets the seed of each point after the first, so the first point matches the single point
+/// placement.
+const POINT_SEED_STEP: u32 = 0x9E37_79B9;
+
+/// Scales a random position in the cell towards its centre, a `jitter` of 0 giving a regular grid.
+fn jittered(position: f32, jitter: f32) -> f32 {
+    position * jitter + 0.5 * (1.0 - jitter)
+}
+
+/// Draws a count from a Poisson distribution with the given mean, by inverting its CDF.
+fn poisson(hash: u32, mean: f32) -> u32 {
+    let target = hash as f32 / u32::MAX as f32;
+
+    let mut probability = (-mean).exp();
+    let mut cumulative = probability;
+    let mut count = 0;
+    while target > cumulative && count < MAX_POINTS_PER_CELL {
+        count += 1;
+        probability *= mean / count as f32;
+        cumulative += probability;
+    }
+
+    count
+}
+
+/// Points in `cell`, relative to its corner, each with a random value of its own.
+///
+/// Without `points_per_cell` every cell has exactly one point, otherwise the count follows a
+/// Poisson distribution with that mean. `cell` must already be wrapped to the period.
+pub fn cell_points_2d(
+    cell: glam::UVec2,
+    seed: u32,
+    jitter: f32,
+    points_per_cell: Option<f32>,
+) -> impl Iterator<Item = (glam::Vec2, u32)> {
+    let count = points_per_cell.map_or(1, |mean| poisson(pcg_31(glam::uvec3(cell.x, cell.y, seed)), mean));
+
+    (0..count).map(move |index| {
+        let point_seed = seed.wrapping_add(index.wrapping_mul(POINT_SEED_STEP));
+        let hash3 = pcg_33(glam::uvec3(cell.x, cell.y, point_seed));
+
+        let position = hash3.xy().as_vec2() / (u32::MAX as f32);
+        (position.map(|position| jittered(position, jitter)), hash3.z)
+    })
+}
+
+/// The 3D counterpart of [`cell_points_2d`].
+pub fn cell_points_3d(
+    cell: glam::UVec3,
+    seed: u32,
+    jitter: f32,
+    points_per_cell: Option<f32>,
+) -> impl Iterator<Item = (glam::Vec3, u32)> {
+    let count = points_per_cell.map_or(1, |mean| poisson(pcg_41(glam::uvec4(cell.x, cell.y, cell.z, seed)), mean));
+
+    (0..count).map(move |index| {
+        let point_seed = seed.wrapping_add(index.wrapping_mul(POINT_SEED_STEP));
+        let hash4 = pcg_44(glam::uvec4(cell.x, cell.y, cell.z, point_seed));
+
+        let position = hash4.xyz().as_vec3() / (u32::MAX as f32);
+        (position.map(|position| jittered(position, jitter)), hash4.w)
+    })
+}
+
+/// The 4D counterpart of [`cell_points_2d`], the point's value hashed from its position.
+pub fn cell_points_4d(
+    cell: glam::UVec4,
+    seed: u32,
+    jitter: f32,
+    points_per_cell: Option<f32>,
+) -> impl Iterator<Item = (glam::Vec4, u32)> {
+    let count = points_per_cell.map_or(1, |mean| poisson(pcg_54(cell, seed).x, mean));
+
+    (0..count).map(move |index| {
+        let point_seed = seed.wrapping_add(index.wrapping_mul(POINT_SEED_STEP));
+        // Flipping the seed keeps the positions apart from the hash the count is drawn from.
+        let hash4 = pcg_54(cell, point_seed ^ POINT_SEED_STEP);
+
+        let position = hash4.as_vec4() / (u32::MAX as f32);
+        (position.map(|position| jittered(position, jitter)), pcg_41(hash4))
+    })
+}
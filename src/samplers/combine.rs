@@ -0,0 +1,294 @@
+use bon::Builder;
+use serde::{Deserialize, Serialize};
+
+use crate::samplers::{NoiseSampler, NoiseSamplerState, SampleParams, Smoothing};
+use crate::util::{mix, MappingFn};
+
+/// An operation on a single noise value.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Transform {
+    Invert,
+    Pow { exponent: f32 },
+    Smooth { smoothing: Smoothing },
+    Remap { from: [f32; 2], to: [f32; 2] },
+    Clamp { min: f32, max: f32 },
+}
+impl Transform {
+    pub fn apply(&self, value: f32) -> f32 {
+        match self {
+            Transform::Invert => 1.0 - value,
+            Transform::Pow { exponent } => value.powf(*exponent),
+            Transform::Smooth { smoothing } => smoothing.smooth(value),
+            Transform::Remap { from, to } => value.remap(from[0], from[1], to[0], to[1]),
+            Transform::Clamp { min, max } => value.clamp(*min, *max),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CombineMode {
+    Add,
+    Subtract,
+    Multiply,
+    Min,
+    Max,
+}
+impl CombineMode {
+    pub fn apply(&self, a: f32, b: f32) -> f32 {
+        match self {
+            CombineMode::Add => a + b,
+            CombineMode::Subtract => a - b,
+            CombineMode::Multiply => a * b,
+            CombineMode::Min => a.min(b),
+            CombineMode::Max => a.max(b),
+        }
+    }
+}
+
+/// Returns the same value everywhere. It has no frequency or seed, so octave parameters are ignored.
+#[derive(Debug, Builder)]
+pub struct ConstantSampler {
+    pub value: f32,
+}
+impl NoiseSampler<f32> for ConstantSampler {
+    fn sample_2d_with(&self, _uv: glam::Vec2, _params: SampleParams) -> f32 {
+        self.value
+    }
+
+    fn sample_3d_with(&self, _uvw: glam::Vec3, _params: SampleParams) -> f32 {
+        self.value
+    }
+}
+impl NoiseSamplerState for ConstantSampler {
+    fn get_frequency(&self) -> f32 {
+        0.0
+    }
+
+    fn get_seed(&self) -> u32 {
+        0
+    }
+
+    fn set_frequency(&mut self, _new_frequency: f32) {}
+
+    fn set_seed(&mut self, _new_seed: u32) {}
+}
+
+/// Applies a [`Transform`] to the output of another sampler.
+#[derive(Debug, Builder)]
+pub struct TransformSampler<S: NoiseSampler<f32>> {
+    sampler: S,
+    transform: Transform,
+}
+impl<S: NoiseSampler<f32>> NoiseSampler<f32> for TransformSampler<S> {
+    fn sample_2d_with(&self, uv: glam::Vec2, params: SampleParams) -> f32 {
+        self.transform.apply(self.sampler.sample_2d_with(uv, params))
+    }
+
+    fn sample_3d_with(&self, uvw: glam::Vec3, params: SampleParams) -> f32 {
+        self.transform.apply(self.sampler.sample_3d_with(uvw, params))
+    }
+
+    fn sample_4d_with(&self, uvwt: glam::Vec4, params: SampleParams) -> f32 {
+        self.transform.apply(self.sampler.sample_4d_with(uvwt, params))
+    }
+
+    fn sample_2d_time_with(&self, uvt: glam::Vec3, params: SampleParams) -> f32 {
+        self.transform.apply(self.sampler.sample_2d_time_with(uvt, params))
+    }
+}
+impl<S: NoiseSampler<f32>> NoiseSamplerState for TransformSampler<S> {
+    fn get_frequency(&self) -> f32 {
+        self.sampler.get_frequency()
+    }
+
+    fn get_seed(&self) -> u32 {
+        self.sampler.get_seed()
+    }
+
+    fn get_period(&self) -> Option<u32> {
+        self.sampler.get_period()
+    }
+
+    fn set_frequency(&mut self, new_frequency: f32) {
+        self.sampler.set_frequency(new_frequency)
+    }
+
+    fn set_seed(&mut self, new_seed: u32) {
+        self.sampler.set_seed(new_seed)
+    }
+}
+
+/// Combines two samplers value by value.
+///
+/// The frequency and seed are those of `a`. Octave parameters are carried over to `b` relative to
+/// its own frequency and seed, so both keep their scale when layered by an FBM.
+#[derive(Debug, Builder)]
+pub struct CombineSampler<A: NoiseSampler<f32>, B: NoiseSampler<f32>> {
+    a: A,
+    b: B,
+    mode: CombineMode,
+}
+impl<A: NoiseSampler<f32>, B: NoiseSampler<f32>> NoiseSampler<f32> for CombineSampler<A, B> {
+    fn sample_2d_with(&self, uv: glam::Vec2, params: SampleParams) -> f32 {
+        let base = self.params();
+
+        self.mode.apply(
+            self.a.sample_2d_with(uv, params),
+            self.b.sample_2d_with(uv, params.rebase(base, self.b.params())),
+        )
+    }
+
+    fn sample_3d_with(&self, uvw: glam::Vec3, params: SampleParams) -> f32 {
+        let base = self.params();
+
+        self.mode.apply(
+            self.a.sample_3d_with(uvw, params),
+            self.b.sample_3d_with(uvw, params.rebase(base, self.b.params())),
+        )
+    }
+
+    fn sample_4d_with(&self, uvwt: glam::Vec4, params: SampleParams) -> f32 {
+        let base = self.params();
+
+        self.mode.apply(
+            self.a.sample_4d_with(uvwt, params),
+            self.b.sample_4d_with(uvwt, params.rebase(base, self.b.params())),
+        )
+    }
+
+    fn sample_2d_time_with(&self, uvt: glam::Vec3, params: SampleParams) -> f32 {
+        let base = self.params();
+
+        self.mode.apply(
+            self.a.sample_2d_time_with(uvt, params),
+            self.b.sample_2d_time_with(uvt, params.rebase(base, self.b.params())),
+        )
+    }
+}
+impl<A: NoiseSampler<f32>, B: NoiseSampler<f32>> NoiseSamplerState for CombineSampler<A, B> {
+    fn get_frequency(&self) -> f32 {
+        self.a.get_frequency()
+    }
+
+    fn get_seed(&self) -> u32 {
+        self.a.get_seed()
+    }
+
+    fn get_period(&self) -> Option<u32> {
+        self.a.get_period()
+    }
+
+    fn set_frequency(&mut self, new_frequency: f32) {
+        let new_params = SampleParams {
+            frequency: new_frequency,
+            seed: self.get_seed(),
+        };
+
+        self.b.set_frequency(new_params.rebase(self.params(), self.b.params()).frequency);
+        self.a.set_frequency(new_frequency);
+    }
+
+    fn set_seed(&mut self, new_seed: u32) {
+        let new_params = SampleParams {
+            frequency: self.get_frequency(),
+            seed: new_seed,
+        };
+
+        self.b.set_seed(new_params.rebase(self.params(), self.b.params()).seed);
+        self.a.set_seed(new_seed);
+    }
+}
+
+/// Interpolates from `a` to `b` by the value of `alpha`.
+///
+/// Like [`CombineSampler`], the frequency and seed are those of `a`.
+#[derive(Debug, Builder)]
+pub struct MixSampler<A: NoiseSampler<f32>, B: NoiseSampler<f32>, T: NoiseSampler<f32>> {
+    a: A,
+    b: B,
+    alpha: T,
+}
+impl<A: NoiseSampler<f32>, B: NoiseSampler<f32>, T: NoiseSampler<f32>> NoiseSampler<f32>
+    for MixSampler<A, B, T>
+{
+    fn sample_2d_with(&self, uv: glam::Vec2, params: SampleParams) -> f32 {
+        let base = self.params();
+
+        mix(
+            self.a.sample_2d_with(uv, params),
+            self.b.sample_2d_with(uv, params.rebase(base, self.b.params())),
+            self.alpha.sample_2d_with(uv, params.rebase(base, self.alpha.params())),
+        )
+    }
+
+    fn sample_3d_with(&self, uvw: glam::Vec3, params: SampleParams) -> f32 {
+        let base = self.params();
+
+        mix(
+            self.a.sample_3d_with(uvw, params),
+            self.b.sample_3d_with(uvw, params.rebase(base, self.b.params())),
+            self.alpha.sample_3d_with(uvw, params.rebase(base, self.alpha.params())),
+        )
+    }
+
+    fn sample_4d_with(&self, uvwt: glam::Vec4, params: SampleParams) -> f32 {
+        let base = self.params();
+
+        mix(
+            self.a.sample_4d_with(uvwt, params),
+            self.b.sample_4d_with(uvwt, params.rebase(base, self.b.params())),
+            self.alpha.sample_4d_with(uvwt, params.rebase(base, self.alpha.params())),
+        )
+    }
+
+    fn sample_2d_time_with(&self, uvt: glam::Vec3, params: SampleParams) -> f32 {
+        let base = self.params();
+
+        mix(
+            self.a.sample_2d_time_with(uvt, params),
+            self.b.sample_2d_time_with(uvt, params.rebase(base, self.b.params())),
+            self.alpha.sample_2d_time_with(uvt, params.rebase(base, self.alpha.params())),
+        )
+    }
+}
+impl<A: NoiseSampler<f32>, B: NoiseSampler<f32>, T: NoiseSampler<f32>> NoiseSamplerState
+    for MixSampler<A, B, T>
+{
+    fn get_frequency(&self) -> f32 {
+        self.a.get_frequency()
+    }
+
+    fn get_seed(&self) -> u32 {
+        self.a.get_seed()
+    }
+
+    fn get_period(&self) -> Option<u32> {
+        self.a.get_period()
+    }
+
+    fn set_frequency(&mut self, new_frequency: f32) {
+        let new_params = SampleParams {
+            frequency: new_frequency,
+            seed: self.get_seed(),
+        };
+        let base = self.params();
+
+        self.b.set_frequency(new_params.rebase(base, self.b.params()).frequency);
+        self.alpha.set_frequency(new_params.rebase(base, self.alpha.params()).frequency);
+        self.a.set_frequency(new_frequency);
+    }
+
+    fn set_seed(&mut self, new_seed: u32) {
+        let new_params = SampleParams {
+            frequency: self.get_frequency(),
+            seed: new_seed,
+        };
+        let base = self.params();
+
+        self.b.set_seed(new_params.rebase(base, self.b.params()).seed);
+        self.alpha.set_seed(new_params.rebase(base, self.alpha.params()).seed);
+        self.a.set_seed(new_seed);
+    }
+}
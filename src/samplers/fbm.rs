@@ -1,56 +1,491 @@
 use std::f32;
+use std::ops::{Add, Mul};
+use std::sync::OnceLock;
 
 use bon::Builder;
+use serde::{Deserialize, Serialize};
 
-use crate::random::hash::pcg_11;
-use crate::samplers::{NoiseSampler, NoiseSamplerState, Smoothing};
+use crate::random::hash::{pcg_11, pcg_44};
+use crate::samplers::{GradientSampler, NoiseSampler, NoiseSamplerState, SampleParams, Smoothing};
 
+/// How the octaves of an [`FbmSampler`] are combined.
+///
+/// The multifractal modes follow Musgrave's formulations, working on the octaves mapped to -1..1.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FbmMode {
+    /// A weighted sum of the octaves.
+    #[default]
+    Standard,
+    /// A weighted sum of the octaves folded around their midpoint, giving puffy, rounded shapes.
+    Billow,
+    /// Sharp ridges at `offset - |noise|`, each octave weighted by the previous one times `gain` so
+    /// detail gathers along the ridges.
+    RidgedMultifractal,
+    /// Each octave, raised by `offset`, weighted by the product of the previous ones, giving smooth
+    /// valleys and rough peaks.
+    HybridMultifractal,
+    /// Each octave, raised by `offset`, scaled by the value so far, so detail grows with height.
+    HeterogeneousTerrain,
+}
+impl FbmMode {
+    /// Combines octaves of signed noise, given as `(noise, derivative, amplitude)`, carrying the
+    /// derivative along.
+    fn accumulate<D: Derivative>(
+        &self,
+        offset: f32,
+        gain: f32,
+        octaves: impl Iterator<Item = (f32, D, f32)>,
+    ) -> (f32, D) {
+        let mut result = 0.0;
+        let mut derivative = D::default();
+        let mut weight = 1.0;
+        let mut weight_derivative = D::default();
+
+        for (index, (noise, noise_derivative, amplitude)) in octaves.enumerate() {
+            match self {
+                FbmMode::Standard => {
+                    result += noise * amplitude;
+                    derivative = derivative + noise_derivative * amplitude;
+                }
+                FbmMode::Billow => {
+                    result += noise.abs() * amplitude;
+                    derivative = derivative + noise_derivative * (noise.signum() * amplitude);
+                }
+                FbmMode::RidgedMultifractal => {
+                    let ridge = offset - noise.abs();
+                    let ridge_derivative = noise_derivative * -noise.signum();
+
+                    let signal = ridge * ridge * weight;
+                    let signal_derivative =
+                        ridge_derivative * (2.0 * ridge * weight) + weight_derivative * (ridge * ridge);
+
+                    result += signal * amplitude;
+                    derivative = derivative + signal_derivative * amplitude;
+
+                    weight = signal * gain;
+                    weight_derivative = if (0.0..=1.0).contains(&weight) {
+                        signal_derivative * gain
+                    } else {
+                        D::default()
+                    };
+                    weight = weight.clamp(0.0, 1.0);
+                }
+                FbmMode::HybridMultifractal => {
+                    if weight > 1.0 {
+                        weight = 1.0;
+                        weight_derivative = D::default();
+                    }
+
+                    let signal = (noise + offset) * amplitude;
+                    let signal_derivative = noise_derivative * amplitude;
+
+                    result += weight * signal;
+                    derivative = derivative + weight_derivative * signal + signal_derivative * weight;
+
+                    weight_derivative = weight_derivative * signal + signal_derivative * weight;
+                    weight *= signal;
+                }
+                FbmMode::HeterogeneousTerrain => {
+                    if index == 0 {
+                        result = noise + offset;
+                        derivative = noise_derivative;
+                    } else {
+                        let scale = (noise + offset) * amplitude;
+
+                        derivative = derivative + noise_derivative * (amplitude * result) + derivative * scale;
+                        result += scale * result;
+                    }
+                }
+            }
+        }
+
+        (result, derivative)
+    }
+}
+
+/// Values the octave loop can carry derivatives in, plain numbers or gradients.
+trait Derivative: Copy + Default + Add<Output = Self> + Mul<f32, Output = Self> {}
+impl<T: Copy + Default + Add<Output = T> + Mul<f32, Output = T>> Derivative for T {}
+
+/// A rotation by atan(4/3) that maps whole-number coordinates onto whole numbers, scaled up by
+/// [`LATTICE_ROTATION_SCALE_2D`].
+const LATTICE_ROTATION_2D: glam::Mat2 = glam::Mat2::from_cols_array(&[3.0, 4.0, -4.0, 3.0]);
+const LATTICE_ROTATION_SCALE_2D: f32 = 5.0;
+
+/// A half turn about the diagonal `(1, 1, 1)` that maps whole-number coordinates onto whole numbers,
+/// scaled up by [`LATTICE_ROTATION_SCALE_3D`].
+const LATTICE_ROTATION_3D: glam::Mat3 =
+    glam::Mat3::from_cols_array(&[-1.0, 2.0, 2.0, 2.0, -1.0, 2.0, 2.0, 2.0, -1.0]);
+const LATTICE_ROTATION_SCALE_3D: f32 = 3.0;
+
+/// Remaps the coordinates of an octave by a random rotation of the lattice and a random offset,
+/// wrapped back into 0..1.
+///
+/// The rotations are integer matrices, which map the lattice onto a scaled copy of itself, so the
+/// octave still tiles. The scale is undone by sampling at a lower frequency, see
+/// [`rotated_frequency`].
+#[derive(Debug, Clone, Copy)]
+struct OctaveTransform {
+    rotation_2d: glam::Mat2,
+    rotation_3d: glam::Mat3,
+    /// The frequencies to sample the octave at, after the rotation's scale.
+    frequency_2d: f32,
+    frequency_3d: f32,
+    offset: glam::Vec3,
+}
+impl OctaveTransform {
+    /// The transform of an octave at `frequency`, of a sampler that only tiles at whole multiples
+    /// of `step`.
+    fn new(hash: glam::UVec4, rotate: bool, offset: bool, frequency: f32, step: f32) -> Self {
+        let (mut rotation_2d, mut rotation_3d) = (glam::Mat2::IDENTITY, glam::Mat3::IDENTITY);
+        let (mut frequency_2d, mut frequency_3d) = (frequency, frequency);
+        if rotate {
+            // A random symmetry of the square or cube, so the octaves don't all turn the same way.
+            let symmetry_2d = signed_permutation::<2>(hash.x).map(glam::Vec2::from_array);
+            let symmetry_3d = signed_permutation::<3>(hash.x).map(glam::Vec3::from_array);
+            rotation_2d = glam::Mat2::from_cols(symmetry_2d[0], symmetry_2d[1]);
+            rotation_3d = glam::Mat3::from_cols(symmetry_3d[0], symmetry_3d[1], symmetry_3d[2]);
+
+            if let Some(rotated) = rotated_frequency(frequency, LATTICE_ROTATION_SCALE_2D, step) {
+                rotation_2d *= LATTICE_ROTATION_2D;
+                frequency_2d = rotated;
+            }
+            if let Some(rotated) = rotated_frequency(frequency, LATTICE_ROTATION_SCALE_3D, step) {
+                rotation_3d *= LATTICE_ROTATION_3D;
+                frequency_3d = rotated;
+            }
+        }
+
+        let offset = if offset {
+            glam::uvec3(hash.y, hash.z, hash.w).as_vec3() / u32::MAX as f32
+        } else {
+            glam::Vec3::ZERO
+        };
+
+        Self {
+            rotation_2d,
+            rotation_3d,
+            frequency_2d,
+            frequency_3d,
+            offset,
+        }
+    }
+
+    fn params_2d(&self, params: SampleParams) -> SampleParams {
+        SampleParams {
+            frequency: self.frequency_2d,
+            ..params
+        }
+    }
+
+    fn params_3d(&self, params: SampleParams) -> SampleParams {
+        SampleParams {
+            frequency: self.frequency_3d,
+            ..params
+        }
+    }
+
+    fn apply_2d(&self, uv: glam::Vec2) -> glam::Vec2 {
+        (self.rotation_2d * uv + self.offset.truncate()).rem_euclid(glam::Vec2::ONE)
+    }
+
+    fn apply_3d(&self, uvw: glam::Vec3) -> glam::Vec3 {
+        (self.rotation_3d * uvw + self.offset).rem_euclid(glam::Vec3::ONE)
+    }
+
+    /// Brings the gradient of a transformed octave back to the untransformed coordinates.
+    fn gradient_2d(&self, gradient: glam::Vec2) -> glam::Vec2 {
+        self.rotation_2d.transpose() * gradient
+    }
+
+    fn gradient_3d(&self, gradient: glam::Vec3) -> glam::Vec3 {
+        self.rotation_3d.transpose() * gradient
+    }
+}
+
+/// The frequency to sample an octave at `frequency` at under a lattice rotation of `scale`: the
+/// nearest whole multiple of `step` to `frequency / scale`, so the octave still tiles.
+///
+/// Low octaves can land far off their nominal frequency that way, `None` if it would be more than
+/// a quarter off, in which case the octave is only swapped and flipped.
+fn rotated_frequency(frequency: f32, scale: f32, step: f32) -> Option<f32> {
+    let rotated = (frequency / (scale * step)).round().max(1.0) * step;
+    ((rotated * scale - frequency).abs() <= frequency * 0.25).then_some(rotated)
+}
+
+/// The columns of a matrix permuting and flipping `N` axes, picked by `hash`.
+fn signed_permutation<const N: usize>(mut hash: u32) -> [[f32; N]; N] {
+    let mut axes: [usize; N] = std::array::from_fn(|axis| axis);
+    for index in (1..N).rev() {
+        axes.swap(index, (hash % (index as u32 + 1)) as usize);
+        hash /= index as u32 + 1;
+    }
+
+    let mut columns = [[0.0; N]; N];
+    for (row, axis) in axes.into_iter().enumerate() {
+        columns[axis][row] = if hash & (1 << row) != 0 { -1.0 } else { 1.0 };
+    }
+    columns
+}
+
+/// A single octave of an [`FbmSampler`].
+#[derive(Debug, Clone, Copy)]
+struct Octave {
+    params: SampleParams,
+    amplitude: f32,
+    transform: Option<OctaveTransform>,
+}
+impl Octave {
+    fn apply_2d(&self, uv: glam::Vec2) -> glam::Vec2 {
+        self.transform.map_or(uv, |transform| transform.apply_2d(uv))
+    }
+
+    fn apply_3d(&self, uvw: glam::Vec3) -> glam::Vec3 {
+        self.transform.map_or(uvw, |transform| transform.apply_3d(uvw))
+    }
+
+    /// Transforms the spatial coordinates, leaving time alone.
+    fn apply_4d(&self, uvwt: glam::Vec4) -> glam::Vec4 {
+        self.apply_3d(uvwt.truncate()).extend(uvwt.w)
+    }
+
+    fn params_2d(&self) -> SampleParams {
+        self.transform.map_or(self.params, |transform| transform.params_2d(self.params))
+    }
+
+    fn params_3d(&self) -> SampleParams {
+        self.transform.map_or(self.params, |transform| transform.params_3d(self.params))
+    }
+
+    fn gradient_2d(&self, gradient: glam::Vec2) -> glam::Vec2 {
+        self.transform.map_or(gradient, |transform| transform.gradient_2d(gradient))
+    }
+
+    fn gradient_3d(&self, gradient: glam::Vec3) -> glam::Vec3 {
+        self.transform.map_or(gradient, |transform| transform.gradient_3d(gradient))
+    }
+}
+
+/// Layers octaves of a sampler at increasing frequencies and decreasing amplitudes.
+///
+/// Each octave multiplies the frequency by `lacunarity` and divides the amplitude by `decay`, or
+/// takes its amplitude from `amplitudes` for as many octaves as it lists. A fractional `octaves`
+/// fades the last octave in by its fractional part, and octaves outside `min_frequency` and
+/// `max_frequency` are left out.
+///
+/// `rotate_octaves` turns every octave after the first so their lattices don't line up, by
+/// atan(4/3) in 2D and a half turn about a diagonal in 3D, each combined with a random axis swap and
+/// flip. Those rotations map the lattice onto itself scaled by 5 in 2D and 3 in 3D, so a rotated
+/// octave samples at its frequency divided by that, rounded to a whole multiple of the wrapped
+/// sampler's frequency over its period (or to a whole number without one) to keep tiling. Octaves
+/// that rounding would move more than a quarter off their frequency are only swapped and flipped.
+/// `offset_octaves` moves every octave after the first by a random offset. The result tiles as long as `lacunarity` is a whole number.
+///
+/// The multifractal modes are normalized by the range they take for constant noise, `offset` and
+/// `gain` only apply to them. That range is not their true extremes, the feedback between octaves
+/// can carry varying noise past it, and those values are clipped to 0..1.
 #[derive(Debug, Builder)]
 pub struct FbmSampler<S: NoiseSampler<f32>> {
     sampler: S,
-    octaves: u32,
+    octaves: f32,
     #[builder(default = 2.0)]
     decay: f32,
     #[builder(default = 2.0)]
     lacunarity: f32,
     #[builder(default = Smoothing::None)]
-    smoothing: Smoothing
+    smoothing: Smoothing,
+    #[builder(default = FbmMode::Standard)]
+    mode: FbmMode,
+    #[builder(default = 1.0)]
+    offset: f32,
+    #[builder(default = 2.0)]
+    gain: f32,
+    amplitudes: Option<Vec<f32>>,
+    #[builder(default = false)]
+    rotate_octaves: bool,
+    #[builder(default = false)]
+    offset_octaves: bool,
+    min_frequency: Option<f32>,
+    max_frequency: Option<f32>,
+    /// The multifractal range at the sampler's own frequency, see [`FbmSampler::bounds`].
+    #[builder(skip)]
+    bounds: OnceLock<(f32, f32)>,
 }
-impl<S: NoiseSampler<f32>> NoiseSampler<f32> for FbmSampler<S> {
-    fn sample_2d(&mut self, uv: glam::Vec2) -> f32 {
-        let mut noise_sum: f32 = 0.0;
-        let mut amplitude_sum: f32 = 0.0;
+impl<S: NoiseSampler<f32>> FbmSampler<S> {
+    /// The frequencies the wrapped sampler tiles at are whole multiples of this: whole numbers, or
+    /// of its frequency over its period if it has one.
+    fn frequency_step(&self) -> f32 {
+        match self.sampler.get_period() {
+            Some(period) => self.sampler.get_frequency() / period as f32,
+            None => 1.0,
+        }
+    }
+
+    /// The octaves to sample, starting from `params`.
+    fn octaves(&self, mut params: SampleParams) -> impl Iterator<Item = Octave> + '_ {
+        let whole_octaves = self.octaves.max(0.0).floor() as u32;
+        let fade = self.octaves.max(0.0).fract();
+        let count = whole_octaves + (fade > 0.0) as u32;
+
+        (0..count).filter_map(move |octave| {
+            let octave_params = params;
+
+            params.frequency *= self.lacunarity;
+            params.seed = pcg_11(params.seed);
 
-        for octave in 0..self.octaves {
-            let sample = self.sampler.sample_2d(uv);
-            let amplitude = (1.0 / self.decay).powf(octave as f32);
+            if self.min_frequency.is_some_and(|min| octave_params.frequency < min)
+                || self.max_frequency.is_some_and(|max| octave_params.frequency > max)
+            {
+                return None;
+            }
 
-            noise_sum += sample * amplitude;
-            amplitude_sum += amplitude;
+            let mut amplitude = match self.amplitudes.as_ref().and_then(|amplitudes| amplitudes.get(octave as usize)) {
+                Some(amplitude) => *amplitude,
+                None => (1.0 / self.decay).powf(octave as f32),
+            };
+            if octave == whole_octaves {
+                amplitude *= fade;
+            }
+
+            let transform = (octave > 0 && (self.rotate_octaves || self.offset_octaves)).then(|| {
+                let hash = pcg_44(glam::uvec4(octave_params.seed, octave, 0, 0));
+                OctaveTransform::new(
+                    hash,
+                    self.rotate_octaves,
+                    self.offset_octaves,
+                    octave_params.frequency,
+                    self.frequency_step(),
+                )
+            });
+
+            Some(Octave {
+                params: octave_params,
+                amplitude,
+                transform,
+            })
+        })
+    }
 
-            self.sampler.set_frequency(self.sampler.get_frequency() * self.lacunarity);
-            self.sampler.set_seed(pcg_11(self.sampler.get_seed()));
+    /// The smallest value and the size of the range the multifractal modes take for constant noise.
+    ///
+    /// Only the octave amplitudes matter, and those only depend on the frequency when octaves are
+    /// left out by frequency, so the range is worked out once and only recomputed for samples at a
+    /// frequency other than the sampler's own.
+    fn bounds(&self, params: SampleParams) -> (f32, f32) {
+        let fixed_octaves = self.min_frequency.is_none() && self.max_frequency.is_none();
+        if fixed_octaves || params.frequency == self.get_frequency() {
+            *self.bounds.get_or_init(|| self.constant_noise_bounds(self.params()))
+        } else {
+            self.constant_noise_bounds(params)
         }
+    }
+
+    fn constant_noise_bounds(&self, params: SampleParams) -> (f32, f32) {
+        let bounds = [-1.0, 0.0, 1.0].map(|noise| {
+            let octaves = self.octaves(params).map(|octave| (noise, 0.0, octave.amplitude));
+            self.mode.accumulate(self.offset, self.gain, octaves).0
+        });
+        let min = bounds.iter().copied().fold(f32::MAX, f32::min);
+        let range = (bounds.iter().copied().fold(f32::MIN, f32::max) - min).max(f32::EPSILON);
 
-        self.smoothing.smooth((noise_sum / amplitude_sum.max(f32::EPSILON)).clamp(0.0, 1.0))
+        (min, range)
     }
 
-    fn sample_3d(&mut self, uvw: glam::Vec3) -> f32 {
-        let mut noise_sum: f32 = 0.0;
-        let mut amplitude_sum: f32 = 0.0;
+    /// Runs `sample` for every octave and combines the results, along with their derivatives.
+    fn sample_octaves<D: Derivative>(
+        &self,
+        params: SampleParams,
+        mut sample: impl FnMut(&Octave) -> (f32, D),
+    ) -> (f32, D) {
+        let (noise, derivative, slope) = match self.mode {
+            FbmMode::Standard => {
+                let mut noise_sum: f32 = 0.0;
+                let mut derivative_sum = D::default();
+                let mut amplitude_sum: f32 = 0.0;
 
-        for octave in 0..self.octaves {
-            let sample = self.sampler.sample_3d(uvw);
-            let amplitude = (1.0 / self.decay).powf(octave as f32);
+                for octave in self.octaves(params) {
+                    let (sample, derivative) = sample(&octave);
 
-            noise_sum += sample * amplitude;
-            amplitude_sum += amplitude;
+                    noise_sum += sample * octave.amplitude;
+                    derivative_sum = derivative_sum + derivative * octave.amplitude;
+                    amplitude_sum += octave.amplitude;
+                }
 
-            self.sampler.set_frequency(self.sampler.get_frequency() * self.lacunarity);
-            self.sampler.set_seed(pcg_11(self.sampler.get_seed()));
-        }
+                (
+                    noise_sum / amplitude_sum.max(f32::EPSILON),
+                    derivative_sum,
+                    1.0 / amplitude_sum.max(f32::EPSILON),
+                )
+            }
+            _ => {
+                let octaves = self.octaves(params).map(|octave| {
+                    let (sample, derivative) = sample(&octave);
+                    (sample * 2.0 - 1.0, derivative * 2.0, octave.amplitude)
+                });
+                let (result, derivative) = self.mode.accumulate(self.offset, self.gain, octaves);
+                let (min, range) = self.bounds(params);
+
+                ((result - min) / range, derivative, 1.0 / range)
+            }
+        };
+
+        let slope = if (0.0..=1.0).contains(&noise) { slope } else { 0.0 };
+        let value = noise.clamp(0.0, 1.0);
+
+        (self.smoothing.smooth(value), derivative * (slope * self.smoothing.derivative(value)))
+    }
+}
+impl<S: NoiseSampler<f32>> NoiseSampler<f32> for FbmSampler<S> {
+    fn sample_2d_with(&self, uv: glam::Vec2, params: SampleParams) -> f32 {
+        self.sample_octaves(params, |octave| {
+            (self.sampler.sample_2d_with(octave.apply_2d(uv), octave.params_2d()), 0.0)
+        })
+        .0
+    }
+
+    fn sample_3d_with(&self, uvw: glam::Vec3, params: SampleParams) -> f32 {
+        self.sample_octaves(params, |octave| {
+            (self.sampler.sample_3d_with(octave.apply_3d(uvw), octave.params_3d()), 0.0)
+        })
+        .0
+    }
+
+    fn sample_4d_with(&self, uvwt: glam::Vec4, params: SampleParams) -> f32 {
+        self.sample_octaves(params, |octave| {
+            (self.sampler.sample_4d_with(octave.apply_4d(uvwt), octave.params_3d()), 0.0)
+        })
+        .0
+    }
 
-        self.smoothing.smooth((noise_sum / amplitude_sum.max(f32::EPSILON)).clamp(0.0, 1.0))
+    fn sample_2d_time_with(&self, uvt: glam::Vec3, params: SampleParams) -> f32 {
+        self.sample_octaves(params, |octave| {
+            let uvt = octave.apply_2d(uvt.truncate()).extend(uvt.z);
+            (self.sampler.sample_2d_time_with(uvt, octave.params_2d()), 0.0)
+        })
+        .0
+    }
+}
+impl<S: GradientSampler> GradientSampler for FbmSampler<S> {
+    fn sample_2d_gradient_with(&self, uv: glam::Vec2, params: SampleParams) -> (f32, glam::Vec2) {
+        self.sample_octaves(params, |octave| {
+            let (value, gradient) = self.sampler.sample_2d_gradient_with(octave.apply_2d(uv), octave.params_2d());
+            (value, octave.gradient_2d(gradient))
+        })
+    }
+
+    fn sample_3d_gradient_with(&self, uvw: glam::Vec3, params: SampleParams) -> (f32, glam::Vec3) {
+        self.sample_octaves(params, |octave| {
+            let (value, gradient) = self.sampler.sample_3d_gradient_with(octave.apply_3d(uvw), octave.params_3d());
+            (value, octave.gradient_3d(gradient))
+        })
+    }
+
+    fn sample_4d_gradient_with(&self, uvwt: glam::Vec4, params: SampleParams) -> (f32, glam::Vec4) {
+        self.sample_octaves(params, |octave| {
+            let (value, gradient) = self.sampler.sample_4d_gradient_with(octave.apply_4d(uvwt), octave.params_3d());
+            (value, octave.gradient_3d(gradient.truncate()).extend(gradient.w))
+        })
     }
 }
 impl<S: NoiseSampler<f32>> NoiseSamplerState for FbmSampler<S> {
@@ -62,11 +497,114 @@ impl<S: NoiseSampler<f32>> NoiseSamplerState for FbmSampler<S> {
         self.sampler.get_seed()
     }
 
+    fn get_period(&self) -> Option<u32> {
+        self.sampler.get_period()
+    }
+
     fn set_frequency(&mut self, new_frequency: f32) {
-        self.sampler.set_frequency(new_frequency)
+        self.sampler.set_frequency(new_frequency);
+        self.bounds = OnceLock::new();
     }
 
     fn set_seed(&mut self, new_seed: u32) {
         self.sampler.set_seed(new_seed)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samplers::perlin::PerlinSampler;
+
+    #[test]
+    fn gradient_matches_central_differences() {
+        let sampler = FbmSampler::builder()
+            .sampler(PerlinSampler::builder().frequency(4.0).seed(5).build())
+            .octaves(4.0)
+            .rotate_octaves(true)
+            .build();
+        let step = 1e-4;
+
+        for uvw in [glam::vec3(0.13, 0.57, 0.91), glam::vec3(0.62, 0.08, 0.35), glam::vec3(0.47, 0.83, 0.21)] {
+            let (value, gradient) = sampler.sample_3d_gradient(uvw);
+            assert!((value - sampler.sample_3d(uvw)).abs() < 1e-5, "value differs at {uvw}");
+
+            for axis in 0..3 {
+                let delta = glam::Vec3::AXES[axis] * step;
+                let expected = (sampler.sample_3d(uvw + delta) - sampler.sample_3d(uvw - delta)) / (2.0 * step);
+                assert!(
+                    (gradient[axis] - expected).abs() < 2e-2 * expected.abs().max(1.0),
+                    "axis {axis} at {uvw}: {} vs {expected}",
+                    gradient[axis]
+                );
+            }
+        }
+
+        for uvwt in [glam::vec4(0.13, 0.57, 0.91, 0.4), glam::vec4(0.62, 0.08, 0.35, 0.9)] {
+            let (value, gradient) = sampler.sample_4d_gradient(uvwt);
+            assert!((value - sampler.sample_4d(uvwt)).abs() < 1e-5, "value differs at {uvwt}");
+
+            for axis in 0..4 {
+                let delta = glam::Vec4::AXES[axis] * step;
+                let expected = (sampler.sample_4d(uvwt + delta) - sampler.sample_4d(uvwt - delta)) / (2.0 * step);
+                assert!(
+                    (gradient[axis] - expected).abs() < 2e-2 * expected.abs().max(1.0),
+                    "axis {axis} at {uvwt}: {} vs {expected}",
+                    gradient[axis]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rotated_octaves_tile() {
+        let sampler = FbmSampler::builder()
+            .sampler(PerlinSampler::builder().frequency(4.0).seed(9).build())
+            .octaves(5.0)
+            .rotate_octaves(true)
+            .build();
+        assert_tiles(&sampler);
+
+        let sampler = FbmSampler::builder()
+            .sampler(PerlinSampler::builder().frequency(8.0).period(4).seed(9).build())
+            .octaves(5.0)
+            .rotate_octaves(true)
+            .build();
+        assert_tiles(&sampler);
+    }
+
+    #[test]
+    fn rotated_octaves_stay_near_their_frequency() {
+        for step in [1.0, 2.0] {
+            for frequency in [1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0] {
+                for scale in [LATTICE_ROTATION_SCALE_2D, LATTICE_ROTATION_SCALE_3D] {
+                    let Some(rotated) = rotated_frequency(frequency, scale, step) else {
+                        continue;
+                    };
+                    assert_eq!(rotated % step, 0.0, "{rotated} isn't a multiple of {step}");
+                    let drift = (rotated * scale / frequency - 1.0).abs();
+                    assert!(drift <= 0.25, "octave at {frequency} drifted by {drift} with scale {scale}");
+                }
+            }
+        }
+    }
+
+    fn assert_tiles(sampler: &FbmSampler<PerlinSampler>) {
+        let step = 1e-4;
+
+        for t in [0.1, 0.35, 0.8] {
+            for axis in 0..3 {
+                let below = glam::Vec3::splat(t) + glam::Vec3::AXES[axis] * (1.0 - t - step);
+                let above = glam::Vec3::splat(t) + glam::Vec3::AXES[axis] * (1.0 - t + step);
+                let jump = (sampler.sample_3d(below) - sampler.sample_3d(above)).abs();
+                assert!(jump < 1e-2, "3D jump of {jump} across the border along axis {axis}");
+            }
+            for axis in 0..2 {
+                let below = glam::Vec2::splat(t) + glam::Vec2::AXES[axis] * (1.0 - t - step);
+                let above = glam::Vec2::splat(t) + glam::Vec2::AXES[axis] * (1.0 - t + step);
+                let jump = (sampler.sample_2d(below) - sampler.sample_2d(above)).abs();
+                assert!(jump < 1e-2, "2D jump of {jump} across the border along axis {axis}");
+            }
+        }
+    }
+}
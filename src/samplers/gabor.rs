@@ -0,0 +1,275 @@
+use std::f32::consts::PI;
+
+use bon::Builder;
+use glam::Vec3Swizzles;
+
+use crate::random::hash::{pcg_11, pcg_33};
+use crate::random::unit::{unit_vector_12, unit_vector_23};
+use crate::samplers::cell::{cell_points_2d, cell_points_3d};
+use crate::samplers::{NoiseSampler, NoiseSamplerState, SampleParams, Smoothing};
+
+/// Which way the stripes of each Gabor kernel run across.
+#[derive(Debug)]
+pub enum GaborOrientation<F: NoiseSampler<glam::Vec3>> {
+    /// Every kernel along the same direction, only its xy part in 2D.
+    Fixed(glam::Vec3),
+    /// A random direction per kernel, which makes the noise isotropic.
+    Random,
+    /// The direction of a vector field at each kernel, so the stripes follow the field.
+    Field(F),
+}
+
+/// Band-limited, oriented noise, a sparse convolution of random impulses with Gabor kernels.
+///
+/// Each kernel is a cosine of `kernel_frequency` cycles per lattice cell under a Gaussian envelope
+/// that `bandwidth` narrows, so a higher bandwidth gives shorter, less regular stripes. The
+/// envelope is cut off at one cell, which shows below a bandwidth of about 1. `spread` blends the
+/// direction of each kernel towards a random one, from perfectly aligned at 0 to isotropic at 1.
+///
+/// The impulses are those of the cellular samplers, `impulses_per_cell` being the mean of their
+/// Poisson distributed count, at most 16.
+#[derive(Debug, Builder)]
+pub struct GaborSampler<F: NoiseSampler<glam::Vec3>> {
+    pub frequency: f32,
+    #[builder(default = 0)]
+    pub seed: u32,
+    /// Lattice cells the noise repeats after at `frequency`, the frequency itself if unset.
+    pub period: Option<u32>,
+    #[builder(default = Smoothing::None)]
+    pub smoothing: Smoothing,
+    #[builder(default = 2.0)]
+    pub kernel_frequency: f32,
+    #[builder(default = 1.5)]
+    pub bandwidth: f32,
+    #[builder(default = 8.0)]
+    pub impulses_per_cell: f32,
+    pub orientation: GaborOrientation<F>,
+    #[builder(default = 0.0)]
+    pub spread: f32,
+}
+impl<F: NoiseSampler<glam::Vec3>> GaborSampler<F> {
+    fn kernel(&self, delta: glam::Vec3, direction: glam::Vec3) -> f32 {
+        let envelope = (-PI * self.bandwidth * self.bandwidth * delta.length_squared()).exp();
+
+        envelope * (2.0 * PI * self.kernel_frequency * delta.dot(direction)).cos()
+    }
+
+    fn direction_2d(&self, impulse: glam::Vec2, hash: u32, params: SampleParams) -> glam::Vec2 {
+        let random = unit_vector_12(hash);
+        let direction = match &self.orientation {
+            GaborOrientation::Fixed(direction) => direction.xy(),
+            GaborOrientation::Random => return random,
+            GaborOrientation::Field(field) => {
+                let field_params = params.rebase(self.params(), field.params());
+                field.sample_2d_with(impulse, field_params).xy()
+            }
+        };
+
+        direction.normalize_or(glam::Vec2::X).lerp(random, self.spread).normalize_or(random)
+    }
+
+    fn direction_3d(&self, impulse: glam::Vec3, hash: u32, params: SampleParams) -> glam::Vec3 {
+        let random = unit_vector_23(pcg_33(glam::uvec3(hash, params.seed, 0)).xy());
+        let direction = match &self.orientation {
+            GaborOrientation::Fixed(direction) => *direction,
+            GaborOrientation::Random => return random,
+            GaborOrientation::Field(field) => {
+                let field_params = params.rebase(self.params(), field.params());
+                field.sample_3d_with(impulse, field_params)
+            }
+        };
+
+        direction.normalize_or(glam::Vec3::X).lerp(random, self.spread).normalize_or(random)
+    }
+
+    /// Maps the sum of kernels, which is centred on 0, to 0..1.
+    ///
+    /// The scale is three standard deviations of the sum, given uniform weights and a Gaussian
+    /// integrated over `dimensions`.
+    fn finish(&self, sum: f32, dimensions: i32) -> f32 {
+        let squared_kernel = 0.5 * (2.0 * self.bandwidth * self.bandwidth).powf(-dimensions as f32 / 2.0);
+        let deviation = (self.impulses_per_cell / 3.0 * squared_kernel).sqrt();
+
+        let value = sum / (3.0 * deviation).max(f32::EPSILON) * 0.5 + 0.5;
+        self.smoothing.smooth(value.clamp(0.0, 1.0))
+    }
+}
+impl<F: NoiseSampler<glam::Vec3>> NoiseSampler<f32> for GaborSampler<F> {
+    fn sample_2d_with(&self, mut uv: glam::Vec2, params: SampleParams) -> f32 {
+        uv *= glam::Vec2::splat(params.frequency);
+
+        let p = uv.floor().as_ivec2();
+        let f = uv.fract_gl();
+        let period = params.period(self.period, self.frequency);
+
+        let mut sum = 0.0;
+        for x in -1..=1 {
+            for y in -1..=1 {
+                let offset = glam::IVec2::new(x, y);
+                let cell = (p + offset).rem_euclid(glam::IVec2::splat(period));
+
+                for (point, hash) in cell_points_2d(cell.as_uvec2(), params.seed, 1.0, Some(self.impulses_per_cell)) {
+                    let delta = point + offset.as_vec2() - f;
+                    let impulse = (uv + delta) / params.frequency;
+
+                    let weight = hash as f32 / u32::MAX as f32 * 2.0 - 1.0;
+                    let direction = self.direction_2d(impulse, pcg_11(hash), params);
+                    sum += weight * self.kernel(delta.extend(0.0), direction.extend(0.0));
+                }
+            }
+        }
+
+        self.finish(sum, 2)
+    }
+
+    fn sample_3d_with(&self, mut uvw: glam::Vec3, params: SampleParams) -> f32 {
+        uvw *= glam::Vec3::splat(params.frequency);
+
+        let p = uvw.floor().as_ivec3();
+        let f = uvw.fract_gl();
+        let period = params.period(self.period, self.frequency);
+
+        let mut sum = 0.0;
+        for x in -1..=1 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    let offset = glam::IVec3::new(x, y, z);
+                    let cell = (p + offset).rem_euclid(glam::IVec3::splat(period));
+
+                    for (point, hash) in cell_points_3d(cell.as_uvec3(), params.seed, 1.0, Some(self.impulses_per_cell)) {
+                        let delta = point + offset.as_vec3() - f;
+                        let impulse = (uvw + delta) / params.frequency;
+
+                        let weight = hash as f32 / u32::MAX as f32 * 2.0 - 1.0;
+                        let direction = self.direction_3d(impulse, pcg_11(hash), params);
+                        sum += weight * self.kernel(delta, direction);
+                    }
+                }
+            }
+        }
+
+        self.finish(sum, 3)
+    }
+}
+impl<F: NoiseSampler<glam::Vec3>> NoiseSamplerState for GaborSampler<F> {
+    fn get_frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    fn get_seed(&self) -> u32 {
+        self.seed
+    }
+
+    fn get_period(&self) -> Option<u32> {
+        self.period
+    }
+
+    fn set_frequency(&mut self, new_frequency: f32) {
+        let new_params = SampleParams {
+            frequency: new_frequency,
+            seed: self.seed,
+        };
+
+        let params = self.params();
+        if let GaborOrientation::Field(field) = &mut self.orientation {
+            field.set_frequency(new_params.rebase(params, field.params()).frequency);
+        }
+        self.frequency = new_frequency;
+    }
+
+    fn set_seed(&mut self, new_seed: u32) {
+        let new_params = SampleParams {
+            frequency: self.frequency,
+            seed: new_seed,
+        };
+
+        let params = self.params();
+        if let GaborOrientation::Field(field) = &mut self.orientation {
+            field.set_seed(new_params.rebase(params, field.params()).seed);
+        }
+        self.seed = new_seed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Orientation = GaborOrientation<Box<dyn NoiseSampler<glam::Vec3>>>;
+
+    fn sampler(orientation: Orientation, period: Option<u32>) -> GaborSampler<Box<dyn NoiseSampler<glam::Vec3>>> {
+        let frequency = if period.is_some() { 6.0 } else { 3.0 };
+        GaborSampler::builder()
+            .frequency(frequency)
+            .maybe_period(period)
+            .seed(8)
+            .orientation(orientation)
+            .build()
+    }
+
+    #[test]
+    fn stays_in_range_and_rarely_clips() {
+        let sampler = sampler(GaborOrientation::Random, None);
+        let (mut sum, mut clipped) = (0.0, 0);
+        let count = 4096;
+
+        for index in 0..count {
+            let uvw = glam::uvec3(index % 64, index / 64, index * 29 % 64).as_vec3() / 64.0;
+            for value in [sampler.sample_2d(uvw.truncate()), sampler.sample_3d(uvw)] {
+                assert!((0.0..=1.0).contains(&value), "value {value} at {uvw}");
+                sum += value;
+                if value == 0.0 || value == 1.0 {
+                    clipped += 1;
+                }
+            }
+        }
+
+        let mean = sum / (2 * count) as f32;
+        assert!((mean - 0.5).abs() < 0.05, "mean of {mean}");
+        assert!(clipped < count / 50, "{clipped} of {} values clipped", 2 * count);
+    }
+
+    /// The kernels are cut off at one cell, so crossing a cell border drops some of them.
+    #[test]
+    fn continuous_across_cells_and_tiles() {
+        let step = 1e-4;
+        for period in [None, Some(2)] {
+            let sampler = sampler(GaborOrientation::Random, period);
+            let cells = sampler.frequency as u32;
+
+            for border in 1..=cells {
+                let border = border as f32 / cells as f32;
+                for t in [0.1, 0.45, 0.8] {
+                    for axis in 0..3 {
+                        let inside = glam::Vec3::splat(t) + glam::Vec3::AXES[axis] * (border - t - step);
+                        let outside = inside + glam::Vec3::AXES[axis] * 2.0 * step;
+                        let jump = (sampler.sample_3d(inside) - sampler.sample_3d(outside)).abs();
+                        assert!(jump < 1e-2, "3D jump of {jump} at {border} along axis {axis}");
+
+                        if axis < 2 {
+                            let (inside, outside) = (inside.truncate(), outside.truncate());
+                            let jump = (sampler.sample_2d(inside) - sampler.sample_2d(outside)).abs();
+                            assert!(jump < 1e-2, "2D jump of {jump} at {border} along axis {axis}");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fixed_orientation_is_anisotropic() {
+        let sampler = sampler(GaborOrientation::Fixed(glam::Vec3::X), None);
+        let step = 1e-3;
+
+        // Stripes across x change quickly along x and only with the envelopes along y.
+        let (mut along_x, mut along_y) = (0.0, 0.0);
+        for index in 0..1024 {
+            let uv = glam::uvec2(index % 32, index / 32).as_vec2() / 32.0;
+            let value = sampler.sample_2d(uv);
+            along_x += (sampler.sample_2d(uv + glam::Vec2::X * step) - value).abs();
+            along_y += (sampler.sample_2d(uv + glam::Vec2::Y * step) - value).abs();
+        }
+        assert!(along_x > 2.0 * along_y, "variation of {along_x} along x and {along_y} along y");
+    }
+}
@@ -1,13 +1,25 @@
+use serde::{Deserialize, Serialize};
+
 use crate::util::SmoothingFn;
 
 pub mod perlin;
+pub mod perlin_worley;
+pub mod simplex;
+pub mod value;
 pub mod vector_field;
+pub mod warp;
 pub mod worley;
 pub mod alligator;
+pub mod blue_noise;
+pub mod cell;
+pub mod combine;
 pub mod fbm;
+pub mod gabor;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Smoothing {
+    #[default]
     None,
     Cubic,
     Quintic
@@ -20,6 +32,60 @@ impl Smoothing {
             Smoothing::Quintic => value.quintic_smooth(),
         }
     }
+
+    /// The derivative of [`Smoothing::smooth`] at `value`.
+    pub fn derivative(&self, value: f32) -> f32 {
+        match self {
+            Smoothing::None => 1.0,
+            _ if !(0.0..=1.0).contains(&value) => 0.0,
+            Smoothing::Cubic => 6.0 * value * (1.0 - value),
+            Smoothing::Quintic => 30.0 * value * value * (value - 1.0) * (value - 1.0),
+        }
+    }
+}
+
+/// The frequency and seed a single evaluation of a sampler runs with.
+///
+/// Samplers that layer octaves work these out per call and pass them down, instead of mutating the
+/// samplers they wrap.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampleParams {
+    pub frequency: f32,
+    pub seed: u32,
+}
+impl SampleParams {
+    /// Carries the change from `base` to `self` over to `other`: the frequency is scaled by the same
+    /// ratio and the seed offset by the same amount.
+    ///
+    /// Samplers wrapping several samplers use this to pass octave parameters on to samplers with
+    /// their own frequency and seed.
+    pub fn rebase(self, base: SampleParams, other: SampleParams) -> SampleParams {
+        let frequency = if base.frequency == 0.0 {
+            other.frequency
+        } else {
+            other.frequency * (self.frequency / base.frequency)
+        };
+
+        SampleParams {
+            frequency,
+            seed: other.seed.wrapping_add(self.seed.wrapping_sub(base.seed)),
+        }
+    }
+
+    /// Lattice cells a sampler wraps around at, given the `period` it was set up with at its own
+    /// `frequency`.
+    ///
+    /// The period is scaled along with the frequency, so octaves keep the same ratio between the
+    /// two. Without one, the sampler wraps at its frequency, which only tiles at whole numbers.
+    pub fn period(self, period: Option<u32>, frequency: f32) -> i32 {
+        let cells = match period {
+            Some(period) if frequency != 0.0 => (period as f32 * (self.frequency / frequency)).round() as i32,
+            Some(period) => period as i32,
+            None => self.frequency as i32,
+        };
+
+        cells.max(1)
+    }
 }
 
 pub trait NoiseSamplerState {
@@ -27,15 +93,136 @@ pub trait NoiseSamplerState {
 
     fn get_seed(&self) -> u32;
 
+    /// Lattice cells the sampler repeats after at its frequency, if it was given a period.
+    ///
+    /// Samplers wrapping others report the period of the one their frequency is taken from.
+    fn get_period(&self) -> Option<u32> {
+        None
+    }
+
     fn set_frequency(&mut self, new_frequency: f32);
 
     fn set_seed(&mut self, new_seed: u32);
+
+    fn params(&self) -> SampleParams {
+        SampleParams {
+            frequency: self.get_frequency(),
+            seed: self.get_seed(),
+        }
+    }
+}
+
+pub trait NoiseSampler<T: Sized>: NoiseSamplerState + Send + Sync {
+    fn sample_2d_with(&self, uv: glam::Vec2, params: SampleParams) -> T;
+
+    fn sample_3d_with(&self, uvw: glam::Vec3, params: SampleParams) -> T;
+
+    /// Samples a 3D volume at time `uvwt.w`, which wraps around like the other axes so the volume
+    /// loops over `0..1`.
+    ///
+    /// Samplers without a time dimension ignore it, which [`crate::Recipe::animation_issues`] warns
+    /// about.
+    fn sample_4d_with(&self, uvwt: glam::Vec4, params: SampleParams) -> T {
+        self.sample_3d_with(uvwt.truncate(), params)
+    }
+
+    /// Samples a 2D texture at time `uvt.z`, looping over `0..1` like [`NoiseSampler::sample_4d_with`].
+    ///
+    /// Most samplers treat time as a third axis, those that differ between 2D and 3D keep to 2D.
+    fn sample_2d_time_with(&self, uvt: glam::Vec3, params: SampleParams) -> T {
+        self.sample_3d_with(uvt, params)
+    }
+
+    fn sample_2d(&self, uv: glam::Vec2) -> T {
+        self.sample_2d_with(uv, self.params())
+    }
+
+    fn sample_3d(&self, uvw: glam::Vec3) -> T {
+        self.sample_3d_with(uvw, self.params())
+    }
+
+    fn sample_4d(&self, uvwt: glam::Vec4) -> T {
+        self.sample_4d_with(uvwt, self.params())
+    }
+
+    fn sample_2d_time(&self, uvt: glam::Vec3) -> T {
+        self.sample_2d_time_with(uvt, self.params())
+    }
 }
 
-pub trait NoiseSampler<T: Sized>: NoiseSamplerState {
-    fn sample_2d(&mut self, uv: glam::Vec2) -> T;
+/// A scalar sampler that can also return its analytic gradient, with respect to the sampled
+/// coordinates.
+pub trait GradientSampler: NoiseSampler<f32> {
+    fn sample_2d_gradient_with(&self, uv: glam::Vec2, params: SampleParams) -> (f32, glam::Vec2);
+
+    fn sample_3d_gradient_with(&self, uvw: glam::Vec3, params: SampleParams) -> (f32, glam::Vec3);
+
+    /// The gradient of [`NoiseSampler::sample_4d_with`], with the time derivative in `w`.
+    fn sample_4d_gradient_with(&self, uvwt: glam::Vec4, params: SampleParams) -> (f32, glam::Vec4);
+
+    fn sample_2d_gradient(&self, uv: glam::Vec2) -> (f32, glam::Vec2) {
+        self.sample_2d_gradient_with(uv, self.params())
+    }
 
-    fn sample_3d(&mut self, uvw: glam::Vec3) -> T;
+    fn sample_3d_gradient(&self, uvw: glam::Vec3) -> (f32, glam::Vec3) {
+        self.sample_3d_gradient_with(uvw, self.params())
+    }
+
+    fn sample_4d_gradient(&self, uvwt: glam::Vec4) -> (f32, glam::Vec4) {
+        self.sample_4d_gradient_with(uvwt, self.params())
+    }
 }
 
+impl<S: NoiseSamplerState + ?Sized> NoiseSamplerState for Box<S> {
+    fn get_frequency(&self) -> f32 {
+        (**self).get_frequency()
+    }
 
+    fn get_seed(&self) -> u32 {
+        (**self).get_seed()
+    }
+
+    fn get_period(&self) -> Option<u32> {
+        (**self).get_period()
+    }
+
+    fn set_frequency(&mut self, new_frequency: f32) {
+        (**self).set_frequency(new_frequency)
+    }
+
+    fn set_seed(&mut self, new_seed: u32) {
+        (**self).set_seed(new_seed)
+    }
+}
+
+impl<T, S: NoiseSampler<T> + ?Sized> NoiseSampler<T> for Box<S> {
+    fn sample_2d_with(&self, uv: glam::Vec2, params: SampleParams) -> T {
+        (**self).sample_2d_with(uv, params)
+    }
+
+    fn sample_3d_with(&self, uvw: glam::Vec3, params: SampleParams) -> T {
+        (**self).sample_3d_with(uvw, params)
+    }
+
+    fn sample_4d_with(&self, uvwt: glam::Vec4, params: SampleParams) -> T {
+        (**self).sample_4d_with(uvwt, params)
+    }
+
+    fn sample_2d_time_with(&self, uvt: glam::Vec3, params: SampleParams) -> T {
+        (**self).sample_2d_time_with(uvt, params)
+    }
+}
+
+impl<S: GradientSampler + ?Sized> GradientSampler for Box<S> {
+    fn sample_2d_gradient_with(&self, uv: glam::Vec2, params: SampleParams) -> (f32, glam::Vec2) {
+        (**self).sample_2d_gradient_with(uv, params)
+    }
+
+    fn sample_3d_gradient_with(&self, uvw: glam::Vec3, params: SampleParams) -> (f32, glam::Vec3) {
+        (**self).sample_3d_gradient_with(uvw, params)
+    }
+
+    fn sample_4d_gradient_with(&self, uvwt: glam::Vec4, params: SampleParams) -> (f32, glam::Vec4) {
+        (**self).sample_4d_gradient_with(uvwt, params)
+    }
+}
@@ -1,19 +1,18 @@
 use std::f32;
 
 use bon::Builder;
-use glam::{Vec3Swizzles, Vec4Swizzles};
-
-use crate::hash::{
-    hash_ivec2, hash_ivec3, pcg_1d, rand_unit_vector_2d, rand_unit_vector_3d, rand_vector_2d,
-    rand_vector_3d,
-};
-use crate::random::hash::{pcg_31, pcg_33, pcg_41, pcg_44};
-use crate::random::unit::{unit_vector_12, unit_vector_23};
-use crate::samplers::{NoiseSampler, NoiseSamplerState, Smoothing};
-use crate::util::{clamp, mix, MappingFn, SmoothingFn};
-
-#[derive(Debug)]
+use glam::Vec4Swizzles;
+use serde::{Deserialize, Serialize};
+
+use crate::random::hash::{pcg_33, pcg_44, pcg_54};
+use crate::random::unit::{unit_vector_12, unit_vector_23, unit_vector_34};
+use crate::samplers::{GradientSampler, NoiseSampler, NoiseSamplerState, SampleParams, Smoothing};
+use crate::util::{corner_4d, mix, mix_corners_4d, MappingFn, SmoothingFn};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PerlinMode {
+    #[default]
     Normal,
     Ridged,
 }
@@ -23,6 +22,8 @@ pub struct PerlinSampler {
     pub frequency: f32,
     #[builder(default = 0)]
     pub seed: u32,
+    /// Lattice cells the noise repeats after at `frequency`, the frequency itself if unset.
+    pub period: Option<u32>,
     #[builder(default = Smoothing::None)]
     pub smoothing: Smoothing,
     #[builder(default = PerlinMode::Normal)]
@@ -31,10 +32,14 @@ pub struct PerlinSampler {
     pub vary_gradient_magnitudes: bool,
 }
 impl PerlinSampler {
-    fn project_2d(&self, pi: glam::IVec2, pf: glam::Vec2, offset: glam::IVec2) -> f32 {
+    fn project_2d(&self, params: SampleParams, pi: glam::IVec2, pf: glam::Vec2, offset: glam::IVec2) -> f32 {
+        (pf - offset.as_vec2()).dot(self.gradient_2d(params, pi, offset))
+    }
+
+    fn gradient_2d(&self, params: SampleParams, pi: glam::IVec2, offset: glam::IVec2) -> glam::Vec2 {
         //  FIXME: Possible quality issue with hash function?
 
-        let cell = (pi + offset).rem_euclid(glam::IVec2::splat(self.frequency as i32));
+        let cell = (pi + offset).rem_euclid(glam::IVec2::splat(params.period(self.period, self.frequency)));
         // let hash = pcg_31(glam::uvec3(
         //     cell.x.cast_unsigned(),
         //     cell.y.cast_unsigned(),
@@ -50,18 +55,22 @@ impl PerlinSampler {
         //     gr
         // };
 
-        let hash = pcg_33(glam::uvec3(cell.x.cast_unsigned(), cell.y.cast_unsigned(), self.seed));
+        let hash = pcg_33(glam::uvec3(cell.x.cast_unsigned(), cell.y.cast_unsigned(), params.seed));
         let mut point = unit_vector_12(hash.x);
 
         if self.vary_gradient_magnitudes {
             point *= (hash.y as f32) / (u32::MAX as f32);
         }
 
-        (pf - offset.as_vec2()).dot(point)
+        point
     }
 
-    fn project_3d(&self, pi: glam::IVec3, pf: glam::Vec3, offset: glam::IVec3) -> f32 {
-        let cell = (pi + offset).rem_euclid(glam::IVec3::splat(self.frequency as i32));
+    fn project_3d(&self, params: SampleParams, pi: glam::IVec3, pf: glam::Vec3, offset: glam::IVec3) -> f32 {
+        (pf - offset.as_vec3()).dot(self.gradient_3d(params, pi, offset))
+    }
+
+    fn gradient_3d(&self, params: SampleParams, pi: glam::IVec3, offset: glam::IVec3) -> glam::Vec3 {
+        let cell = (pi + offset).rem_euclid(glam::IVec3::splat(params.period(self.period, self.frequency)));
         // let hash = pcg_41(glam::uvec4(
         //     cell.x.cast_unsigned(),
         //     cell.y.cast_unsigned(),
@@ -86,7 +95,7 @@ impl PerlinSampler {
             cell.x.cast_unsigned(),
             cell.y.cast_unsigned(),
             cell.z.cast_unsigned(),
-            self.seed,
+            params.seed,
         ));
         let mut point = unit_vector_23(hash.xy());
 
@@ -94,22 +103,53 @@ impl PerlinSampler {
             point *= (hash.z as f32) / (u32::MAX as f32);
         }
 
-        (pf - offset.as_vec3()).dot(point)
+        point
+    }
+
+    fn gradient_4d(&self, params: SampleParams, pi: glam::IVec4, offset: glam::IVec4) -> glam::Vec4 {
+        let cell = (pi + offset).rem_euclid(glam::IVec4::splat(params.period(self.period, self.frequency)));
+        let hash = pcg_54(cell.as_uvec4(), params.seed);
+        let mut point = unit_vector_34(hash.xyz());
+
+        if self.vary_gradient_magnitudes {
+            point *= (hash.w as f32) / (u32::MAX as f32);
+        }
+
+        point
+    }
+
+    /// Maps raw noise and its gradient to the output range, following the mode and smoothing.
+    fn finish_gradient<G>(&self, noise: f32, gradient: G) -> (f32, G)
+    where
+        G: std::ops::Mul<f32, Output = G>,
+    {
+        let value = noise.clamped_map(-1.0, 1.0);
+        let slope = if (-1.0..=1.0).contains(&noise) { 0.5 } else { 0.0 };
+
+        let (value, slope) = match &self.mode {
+            PerlinMode::Normal => (value, slope),
+            PerlinMode::Ridged => {
+                let centered = value * 2.0 - 1.0;
+                (centered.abs(), slope * 2.0 * centered.signum())
+            }
+        };
+
+        (self.smoothing.smooth(value), gradient * (slope * self.smoothing.derivative(value)))
     }
 }
 impl NoiseSampler<f32> for PerlinSampler {
-    fn sample_2d(&mut self, mut uv: glam::Vec2) -> f32 {
-        uv *= self.frequency;
+    fn sample_2d_with(&self, mut uv: glam::Vec2, params: SampleParams) -> f32 {
+        uv *= params.frequency;
 
         let pi = uv.floor().as_ivec2();
-        let pf = uv.fract().abs();
+        let pf = uv.fract_gl();
 
         let f = pf.quintic_smooth();
 
-        let p00 = self.project_2d(pi, pf, glam::ivec2(0, 0));
-        let p10 = self.project_2d(pi, pf, glam::ivec2(1, 0));
-        let p01 = self.project_2d(pi, pf, glam::ivec2(0, 1));
-        let p11 = self.project_2d(pi, pf, glam::ivec2(1, 1));
+        let p00 = self.project_2d(params, pi, pf, glam::ivec2(0, 0));
+        let p10 = self.project_2d(params, pi, pf, glam::ivec2(1, 0));
+        let p01 = self.project_2d(params, pi, pf, glam::ivec2(0, 1));
+        let p11 = self.project_2d(params, pi, pf, glam::ivec2(1, 1));
 
         let value = mix(mix(p00, p10, f.x), mix(p01, p11, f.x), f.y).clamped_map(-1.0, 1.0);
         match &self.mode {
@@ -118,22 +158,22 @@ impl NoiseSampler<f32> for PerlinSampler {
         }
     }
 
-    fn sample_3d(&mut self, mut uvw: glam::Vec3) -> f32 {
-        uvw *= self.frequency;
+    fn sample_3d_with(&self, mut uvw: glam::Vec3, params: SampleParams) -> f32 {
+        uvw *= params.frequency;
 
         let pi = uvw.floor().as_ivec3();
-        let pf = uvw.fract();
+        let pf = uvw.fract_gl();
 
         let f = pf.quintic_smooth();
 
-        let p000 = self.project_3d(pi, pf, glam::ivec3(0, 0, 0));
-        let p001 = self.project_3d(pi, pf, glam::ivec3(0, 0, 1));
-        let p010 = self.project_3d(pi, pf, glam::ivec3(0, 1, 0));
-        let p011 = self.project_3d(pi, pf, glam::ivec3(0, 1, 1));
-        let p100 = self.project_3d(pi, pf, glam::ivec3(1, 0, 0));
-        let p101 = self.project_3d(pi, pf, glam::ivec3(1, 0, 1));
-        let p110 = self.project_3d(pi, pf, glam::ivec3(1, 1, 0));
-        let p111 = self.project_3d(pi, pf, glam::ivec3(1, 1, 1));
+        let p000 = self.project_3d(params, pi, pf, glam::ivec3(0, 0, 0));
+        let p001 = self.project_3d(params, pi, pf, glam::ivec3(0, 0, 1));
+        let p010 = self.project_3d(params, pi, pf, glam::ivec3(0, 1, 0));
+        let p011 = self.project_3d(params, pi, pf, glam::ivec3(0, 1, 1));
+        let p100 = self.project_3d(params, pi, pf, glam::ivec3(1, 0, 0));
+        let p101 = self.project_3d(params, pi, pf, glam::ivec3(1, 0, 1));
+        let p110 = self.project_3d(params, pi, pf, glam::ivec3(1, 1, 0));
+        let p111 = self.project_3d(params, pi, pf, glam::ivec3(1, 1, 1));
 
         let x00 = mix(p000, p100, f.x);
         let x01 = mix(p001, p101, f.x);
@@ -149,6 +189,154 @@ impl NoiseSampler<f32> for PerlinSampler {
             PerlinMode::Ridged => self.smoothing.smooth((value * 2.0 - 1.0).abs()),
         }
     }
+
+    fn sample_4d_with(&self, mut uvwt: glam::Vec4, params: SampleParams) -> f32 {
+        uvwt *= params.frequency;
+
+        let pi = uvwt.floor().as_ivec4();
+        let pf = uvwt - pi.as_vec4();
+
+        let corners = std::array::from_fn(|index| {
+            let offset = corner_4d(index);
+            (pf - offset.as_vec4()).dot(self.gradient_4d(params, pi, offset))
+        });
+
+        let value = mix_corners_4d(corners, pf.quintic_smooth()).clamped_map(-1.0, 1.0);
+        match &self.mode {
+            PerlinMode::Normal => self.smoothing.smooth(value),
+            PerlinMode::Ridged => self.smoothing.smooth((value * 2.0 - 1.0).abs()),
+        }
+    }
+}
+/// The value is interpolated as in [`NoiseSampler`], written out as a polynomial so its derivative
+/// can be taken along with it.
+impl GradientSampler for PerlinSampler {
+    fn sample_2d_gradient_with(&self, mut uv: glam::Vec2, params: SampleParams) -> (f32, glam::Vec2) {
+        uv *= params.frequency;
+
+        let pi = uv.floor().as_ivec2();
+        let pf = uv - pi.as_vec2();
+
+        let u = pf.quintic_smooth();
+        let du = 30.0 * pf * pf * (pf * (pf - 2.0) + 1.0);
+
+        let ga = self.gradient_2d(params, pi, glam::ivec2(0, 0));
+        let gb = self.gradient_2d(params, pi, glam::ivec2(1, 0));
+        let gc = self.gradient_2d(params, pi, glam::ivec2(0, 1));
+        let gd = self.gradient_2d(params, pi, glam::ivec2(1, 1));
+
+        let a = ga.dot(pf);
+        let b = gb.dot(pf - glam::vec2(1.0, 0.0));
+        let c = gc.dot(pf - glam::vec2(0.0, 1.0));
+        let d = gd.dot(pf - glam::vec2(1.0, 1.0));
+
+        let k1 = b - a;
+        let k2 = c - a;
+        let k4 = a - b - c + d;
+
+        let noise = a + k1 * u.x + k2 * u.y + k4 * u.x * u.y;
+        let gradient = ga
+            + u.x * (gb - ga)
+            + u.y * (gc - ga)
+            + u.x * u.y * (ga - gb - gc + gd)
+            + du * glam::vec2(k1 + k4 * u.y, k2 + k4 * u.x);
+
+        self.finish_gradient(noise, gradient * params.frequency)
+    }
+
+    fn sample_3d_gradient_with(&self, mut uvw: glam::Vec3, params: SampleParams) -> (f32, glam::Vec3) {
+        uvw *= params.frequency;
+
+        let pi = uvw.floor().as_ivec3();
+        let pf = uvw - pi.as_vec3();
+
+        let u = pf.quintic_smooth();
+        let du = 30.0 * pf * pf * (pf * (pf - 2.0) + 1.0);
+
+        let ga = self.gradient_3d(params, pi, glam::ivec3(0, 0, 0));
+        let gb = self.gradient_3d(params, pi, glam::ivec3(1, 0, 0));
+        let gc = self.gradient_3d(params, pi, glam::ivec3(0, 1, 0));
+        let gd = self.gradient_3d(params, pi, glam::ivec3(1, 1, 0));
+        let ge = self.gradient_3d(params, pi, glam::ivec3(0, 0, 1));
+        let gf = self.gradient_3d(params, pi, glam::ivec3(1, 0, 1));
+        let gg = self.gradient_3d(params, pi, glam::ivec3(0, 1, 1));
+        let gh = self.gradient_3d(params, pi, glam::ivec3(1, 1, 1));
+
+        let a = ga.dot(pf);
+        let b = gb.dot(pf - glam::vec3(1.0, 0.0, 0.0));
+        let c = gc.dot(pf - glam::vec3(0.0, 1.0, 0.0));
+        let d = gd.dot(pf - glam::vec3(1.0, 1.0, 0.0));
+        let e = ge.dot(pf - glam::vec3(0.0, 0.0, 1.0));
+        let f = gf.dot(pf - glam::vec3(1.0, 0.0, 1.0));
+        let g = gg.dot(pf - glam::vec3(0.0, 1.0, 1.0));
+        let h = gh.dot(pf - glam::vec3(1.0, 1.0, 1.0));
+
+        let k1 = b - a;
+        let k2 = c - a;
+        let k3 = e - a;
+        let k4 = a - b - c + d;
+        let k5 = a - c - e + g;
+        let k6 = a - b - e + f;
+        let k7 = -a + b + c - d + e - f - g + h;
+
+        let noise = a
+            + k1 * u.x
+            + k2 * u.y
+            + k3 * u.z
+            + k4 * u.x * u.y
+            + k5 * u.y * u.z
+            + k6 * u.z * u.x
+            + k7 * u.x * u.y * u.z;
+        let gradient = ga
+            + u.x * (gb - ga)
+            + u.y * (gc - ga)
+            + u.z * (ge - ga)
+            + u.x * u.y * (ga - gb - gc + gd)
+            + u.y * u.z * (ga - gc - ge + gg)
+            + u.z * u.x * (ga - gb - ge + gf)
+            + u.x * u.y * u.z * (-ga + gb + gc - gd + ge - gf - gg + gh)
+            + du * glam::vec3(
+                k1 + k4 * u.y + k6 * u.z + k7 * u.y * u.z,
+                k2 + k5 * u.z + k4 * u.x + k7 * u.z * u.x,
+                k3 + k6 * u.x + k5 * u.y + k7 * u.x * u.y,
+            );
+
+        self.finish_gradient(noise, gradient * params.frequency)
+    }
+
+    /// Summed over the 16 corners instead of written out, each corner weighted by the product of
+    /// its smoothed weights along every axis.
+    fn sample_4d_gradient_with(&self, mut uvwt: glam::Vec4, params: SampleParams) -> (f32, glam::Vec4) {
+        uvwt *= params.frequency;
+
+        let pi = uvwt.floor().as_ivec4();
+        let pf = uvwt - pi.as_vec4();
+
+        let u = pf.quintic_smooth();
+        let du = 30.0 * pf * pf * (pf * (pf - 2.0) + 1.0);
+
+        let mut noise = 0.0;
+        let mut gradient = glam::Vec4::ZERO;
+        for index in 0..16 {
+            let offset = corner_4d(index);
+            let corner = offset.as_vec4();
+            let g = self.gradient_4d(params, pi, offset);
+            let projection = (pf - corner).dot(g);
+
+            let weights = corner * u + (1.0 - corner) * (1.0 - u);
+            let slopes = (corner * 2.0 - 1.0) * du;
+            let weight_slopes = glam::Vec4::from_array(std::array::from_fn(|axis| {
+                let mut weights = weights;
+                weights[axis] = slopes[axis];
+                weights.element_product()
+            }));
+
+            noise += weights.element_product() * projection;
+            gradient += g * weights.element_product() + weight_slopes * projection;
+        }
+
+        self.finish_gradient(noise, gradient * params.frequency)
+    }
 }
 impl NoiseSamplerState for PerlinSampler {
     fn get_frequency(&self) -> f32 {
@@ -159,6 +347,10 @@ impl NoiseSamplerState for PerlinSampler {
         self.seed
     }
 
+    fn get_period(&self) -> Option<u32> {
+        self.period
+    }
+
     fn set_frequency(&mut self, new_frequency: f32) {
         self.frequency = new_frequency;
     }
@@ -167,3 +359,50 @@ impl NoiseSamplerState for PerlinSampler {
         self.seed = new_seed;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gradient_matches_central_differences() {
+        let sampler = PerlinSampler::builder().frequency(4.0).seed(3).build();
+        let step = 1e-3;
+
+        for uvw in [glam::vec3(0.13, 0.57, 0.91), glam::vec3(0.62, 0.08, 0.35), glam::vec3(-0.29, -0.71, 0.44)] {
+            let (value, gradient) = sampler.sample_3d_gradient(uvw);
+            assert!((value - sampler.sample_3d(uvw)).abs() < 1e-5, "value differs at {uvw}");
+
+            for axis in 0..3 {
+                let delta = glam::Vec3::AXES[axis] * step;
+                let expected = (sampler.sample_3d(uvw + delta) - sampler.sample_3d(uvw - delta)) / (2.0 * step);
+                assert!(
+                    (gradient[axis] - expected).abs() < 1e-2 * expected.abs().max(1.0),
+                    "axis {axis} at {uvw}: {} vs {expected}",
+                    gradient[axis]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn gradient_4d_matches_central_differences() {
+        let sampler = PerlinSampler::builder().frequency(4.0).seed(3).build();
+        let step = 1e-3;
+
+        for uvwt in [glam::vec4(0.13, 0.57, 0.91, 0.26), glam::vec4(-0.62, 0.08, -0.35, 0.74)] {
+            let (value, gradient) = sampler.sample_4d_gradient(uvwt);
+            assert!((value - sampler.sample_4d(uvwt)).abs() < 1e-5, "value differs at {uvwt}");
+
+            for axis in 0..4 {
+                let delta = glam::Vec4::AXES[axis] * step;
+                let expected = (sampler.sample_4d(uvwt + delta) - sampler.sample_4d(uvwt - delta)) / (2.0 * step);
+                assert!(
+                    (gradient[axis] - expected).abs() < 1e-2 * expected.abs().max(1.0),
+                    "axis {axis} at {uvwt}: {} vs {expected}",
+                    gradient[axis]
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use bon::Builder;
+
+use crate::random::hash::pcg_11;
+use crate::samplers::fbm::FbmSampler;
+use crate::samplers::perlin::PerlinSampler;
+use crate::samplers::worley::{WorleyMode, WorleySampler};
+use crate::samplers::{NoiseSampler, NoiseSamplerState, SampleParams, Smoothing};
+use crate::util::MappingFn;
+
+/// Weights of the three Worley layers, from the lowest frequency up.
+const WORLEY_WEIGHTS: [f32; 3] = [0.625, 0.25, 0.125];
+
+/// The billowy Perlin-Worley noise used for cloud base shapes.
+///
+/// A Perlin FBM is remapped from `(worley - 1) * blend..1` to `0..1`, where `worley` is the sum
+/// of three inverted F1 Worley layers. This dilates the Perlin noise into round, cauliflower-like
+/// shapes wherever the Worley cells are bright. `worley_frequencies` are multiples of `frequency`,
+/// and must be whole numbers for the result to tile.
+///
+/// The Perlin FBM and the Worley layers are built along with the sampler, and sampled with the
+/// parameters of each call carried over to them.
+#[derive(Debug, Builder)]
+pub struct PerlinWorleySampler {
+    pub frequency: f32,
+    #[builder(default = 0)]
+    pub seed: u32,
+    /// Lattice cells the noise repeats after at `frequency`, the frequency itself if unset. The
+    /// Worley layers repeat after the same distance.
+    pub period: Option<u32>,
+    #[builder(default = 3)]
+    pub perlin_octaves: u32,
+    #[builder(default = [1.0, 2.0, 4.0])]
+    pub worley_frequencies: [f32; 3],
+    #[builder(default = 1.0)]
+    pub blend: f32,
+    #[builder(default = Smoothing::None)]
+    pub smoothing: Smoothing,
+    #[builder(skip = perlin(frequency, seed, period, perlin_octaves))]
+    perlin: FbmSampler<PerlinSampler>,
+    #[builder(skip = worley_layers(frequency, seed, period, worley_frequencies))]
+    worley: [WorleySampler; 3],
+}
+impl PerlinWorleySampler {
+    fn finish(&self, perlin: f32, worley: f32) -> f32 {
+        let value = perlin.remap((worley - 1.0) * self.blend, 1.0, 0.0, 1.0);
+        self.smoothing.smooth(value.clamp(0.0, 1.0))
+    }
+
+    /// Samples the Perlin FBM and the weighted sum of the Worley layers, each with `params`
+    /// carried over from this sampler to it.
+    fn sample_layers<S>(&self, params: SampleParams, sample: S) -> f32
+    where
+        S: Fn(&dyn NoiseSampler<f32>, SampleParams) -> f32,
+    {
+        let base = self.params();
+        let perlin = sample(&self.perlin, params.rebase(base, self.perlin.params()));
+        let worley = self
+            .worley
+            .iter()
+            .zip(WORLEY_WEIGHTS)
+            .map(|(layer, weight)| sample(layer, params.rebase(base, layer.params())) * weight)
+            .sum();
+
+        self.finish(perlin, worley)
+    }
+}
+impl NoiseSampler<f32> for PerlinWorleySampler {
+    fn sample_2d_with(&self, uv: glam::Vec2, params: SampleParams) -> f32 {
+        self.sample_layers(params, |sampler, params| sampler.sample_2d_with(uv, params))
+    }
+
+    fn sample_3d_with(&self, uvw: glam::Vec3, params: SampleParams) -> f32 {
+        self.sample_layers(params, |sampler, params| sampler.sample_3d_with(uvw, params))
+    }
+
+    fn sample_4d_with(&self, uvwt: glam::Vec4, params: SampleParams) -> f32 {
+        self.sample_layers(params, |sampler, params| sampler.sample_4d_with(uvwt, params))
+    }
+}
+impl NoiseSamplerState for PerlinWorleySampler {
+    fn get_frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    fn get_seed(&self) -> u32 {
+        self.seed
+    }
+
+    fn get_period(&self) -> Option<u32> {
+        self.period
+    }
+
+    fn set_frequency(&mut self, new_frequency: f32) {
+        self.frequency = new_frequency;
+        self.perlin = perlin(self.frequency, self.seed, self.period, self.perlin_octaves);
+        self.worley = worley_layers(self.frequency, self.seed, self.period, self.worley_frequencies);
+    }
+
+    fn set_seed(&mut self, new_seed: u32) {
+        self.seed = new_seed;
+        self.perlin = perlin(self.frequency, self.seed, self.period, self.perlin_octaves);
+        self.worley = worley_layers(self.frequency, self.seed, self.period, self.worley_frequencies);
+    }
+}
+
+fn perlin(frequency: f32, seed: u32, period: Option<u32>, octaves: u32) -> FbmSampler<PerlinSampler> {
+    let perlin = PerlinSampler::builder()
+        .frequency(frequency)
+        .seed(seed)
+        .maybe_period(period)
+        .build();
+
+    FbmSampler::builder().sampler(perlin).octaves(octaves as f32).build()
+}
+
+/// The Worley layers, each with its own seed so their points don't line up with the Perlin
+/// gradients.
+fn worley_layers(frequency: f32, seed: u32, period: Option<u32>, multiples: [f32; 3]) -> [WorleySampler; 3] {
+    std::array::from_fn(|layer| {
+        WorleySampler::builder()
+            .frequency(frequency * multiples[layer])
+            .seed(pcg_11(seed.wrapping_add(layer as u32 + 1)))
+            .maybe_period(period.map(|cells| (cells as f32 * multiples[layer]).round() as u32))
+            .mode(WorleyMode::OneMinusF1)
+            .build()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stays_in_range_and_tiles() {
+        let sampler = PerlinWorleySampler::builder().frequency(3.0).seed(11).build();
+        let step = 1e-4;
+
+        for index in 0..512 {
+            let uvw = glam::vec3(index as f32 * 0.618, index as f32 * 0.414, index as f32 * 0.732).fract();
+            let value = sampler.sample_3d(uvw);
+            assert!((0.0..=1.0).contains(&value), "{value} at {uvw}");
+        }
+
+        for t in [0.2, 0.55, 0.9] {
+            for axis in 0..3 {
+                let below = glam::Vec3::splat(t) + glam::Vec3::AXES[axis] * (1.0 - t - step);
+                let above = glam::Vec3::splat(t) + glam::Vec3::AXES[axis] * (1.0 - t + step);
+                let jump = (sampler.sample_3d(below) - sampler.sample_3d(above)).abs();
+                assert!(jump < 1e-2, "jump of {jump} across the border along axis {axis}");
+            }
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use bon::Builder;
+use glam::Vec4Swizzles;
+use serde::{Deserialize, Serialize};
+
+use crate::random::hash::{pcg_33, pcg_44, pcg_54};
+use crate::random::unit::{unit_vector_12, unit_vector_23, unit_vector_34};
+use crate::samplers::{NoiseSampler, NoiseSamplerState, SampleParams, Smoothing};
+use crate::util::{corner_4d, MappingFn};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SimplexMode {
+    #[default]
+    Normal,
+    Ridged,
+}
+
+/// Kernel size, following the two OpenSimplex2 variants.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SimplexVariant {
+    /// Smaller kernels, as in OpenSimplex2.
+    #[default]
+    Fast,
+    /// Larger, smoother kernels, as in OpenSimplex2S.
+    Smooth,
+}
+impl SimplexVariant {
+    fn radius_squared_2d(&self) -> f32 {
+        match self {
+            SimplexVariant::Fast => 0.5,
+            SimplexVariant::Smooth => 2.0 / 3.0,
+        }
+    }
+
+    fn radius_squared_3d(&self) -> f32 {
+        match self {
+            SimplexVariant::Fast => 0.6,
+            SimplexVariant::Smooth => 0.75,
+        }
+    }
+
+    /// The two offset hypercubic lattices leave no point further than `sqrt(0.5)` from a lattice
+    /// point, so the kernels have to reach beyond that.
+    fn radius_squared_4d(&self) -> f32 {
+        match self {
+            SimplexVariant::Fast => 0.75,
+            SimplexVariant::Smooth => 1.0,
+        }
+    }
+
+    /// Scales the kernel sum to just within -1..1, found by sampling dense grids over many seeds.
+    fn normalizer_2d(&self) -> f32 {
+        match self {
+            SimplexVariant::Fast => 70.0,
+            SimplexVariant::Smooth => 16.0,
+        }
+    }
+
+    fn normalizer_3d(&self) -> f32 {
+        match self {
+            SimplexVariant::Fast => 40.0,
+            SimplexVariant::Smooth => 11.5,
+        }
+    }
+
+    fn normalizer_4d(&self) -> f32 {
+        match self {
+            SimplexVariant::Fast => 16.5,
+            SimplexVariant::Smooth => 3.0,
+        }
+    }
+}
+
+/// Gradient noise on the OpenSimplex2 body-centred cubic lattice.
+///
+/// The lattice is made of two cubic lattices offset by half a cell, each point contributing a
+/// radial kernel rather than being interpolated along the axes, which avoids the axis-aligned
+/// artifacts of Perlin noise. The lattice is left unrotated so it tiles at integer frequencies.
+///
+/// 2D uses the same construction, a square lattice with a point at the centre of every cell, not the
+/// triangular lattice of OpenSimplex2, so it keeps some of the square grid's directional bias.
+#[derive(Debug, Builder)]
+pub struct SimplexSampler {
+    pub frequency: f32,
+    #[builder(default = 0)]
+    pub seed: u32,
+    /// Lattice cells the noise repeats after at `frequency`, the frequency itself if unset.
+    pub period: Option<u32>,
+    #[builder(default = Smoothing::None)]
+    pub smoothing: Smoothing,
+    #[builder(default = SimplexMode::Normal)]
+    pub mode: SimplexMode,
+    #[builder(default = SimplexVariant::Fast)]
+    pub variant: SimplexVariant,
+}
+impl SimplexSampler {
+    fn finish(&self, value: f32) -> f32 {
+        let value = value.clamped_map(-1.0, 1.0);
+        match &self.mode {
+            SimplexMode::Normal => self.smoothing.smooth(value),
+            SimplexMode::Ridged => self.smoothing.smooth((value * 2.0 - 1.0).abs()),
+        }
+    }
+}
+impl NoiseSampler<f32> for SimplexSampler {
+    fn sample_2d_with(&self, mut uv: glam::Vec2, params: SampleParams) -> f32 {
+        uv *= params.frequency;
+
+        let period = glam::IVec2::splat(params.period(self.period, self.frequency));
+        let radius_squared = self.variant.radius_squared_2d();
+
+        let mut value = 0.0;
+        for lattice in 0..2 {
+            let shifted = uv - 0.5 * lattice as f32;
+            let pi = shifted.floor().as_ivec2();
+            let pf = shifted - pi.as_vec2();
+
+            for x in 0..=1 {
+                for y in 0..=1 {
+                    let offset = glam::ivec2(x, y);
+                    let delta = pf - offset.as_vec2();
+
+                    let falloff = radius_squared - delta.length_squared();
+                    if falloff <= 0.0 {
+                        continue;
+                    }
+
+                    let cell = ((pi + offset).rem_euclid(period) * 2 + lattice).as_uvec2();
+                    let hash = pcg_33(glam::uvec3(cell.x, cell.y, params.seed));
+                    let gradient = unit_vector_12(hash.x);
+
+                    value += falloff.powi(4) * delta.dot(gradient);
+                }
+            }
+        }
+
+        self.finish(value * self.variant.normalizer_2d())
+    }
+
+    fn sample_3d_with(&self, mut uvw: glam::Vec3, params: SampleParams) -> f32 {
+        uvw *= params.frequency;
+
+        let period = glam::IVec3::splat(params.period(self.period, self.frequency));
+        let radius_squared = self.variant.radius_squared_3d();
+
+        let mut value = 0.0;
+        for lattice in 0..2 {
+            let shifted = uvw - 0.5 * lattice as f32;
+            let pi = shifted.floor().as_ivec3();
+            let pf = shifted - pi.as_vec3();
+
+            for x in 0..=1 {
+                for y in 0..=1 {
+                    for z in 0..=1 {
+                        let offset = glam::ivec3(x, y, z);
+                        let delta = pf - offset.as_vec3();
+
+                        let falloff = radius_squared - delta.length_squared();
+                        if falloff <= 0.0 {
+                            continue;
+                        }
+
+                        let cell = ((pi + offset).rem_euclid(period) * 2 + lattice).as_uvec3();
+                        let hash = pcg_44(glam::uvec4(cell.x, cell.y, cell.z, params.seed));
+                        let gradient = unit_vector_23(hash.xy());
+
+                        value += falloff.powi(4) * delta.dot(gradient);
+                    }
+                }
+            }
+        }
+
+        self.finish(value * self.variant.normalizer_3d())
+    }
+
+    fn sample_4d_with(&self, mut uvwt: glam::Vec4, params: SampleParams) -> f32 {
+        uvwt *= params.frequency;
+
+        let period = glam::IVec4::splat(params.period(self.period, self.frequency));
+        let radius_squared = self.variant.radius_squared_4d();
+
+        let mut value = 0.0;
+        for lattice in 0..2 {
+            let shifted = uvwt - 0.5 * lattice as f32;
+            let pi = shifted.floor().as_ivec4();
+            let pf = shifted - pi.as_vec4();
+
+            for corner in 0..16 {
+                let offset = corner_4d(corner);
+                let delta = pf - offset.as_vec4();
+
+                let falloff = radius_squared - delta.length_squared();
+                if falloff <= 0.0 {
+                    continue;
+                }
+
+                let cell = ((pi + offset).rem_euclid(period) * 2 + lattice).as_uvec4();
+                let hash = pcg_54(cell, params.seed);
+                let gradient = unit_vector_34(hash.xyz());
+
+                value += falloff.powi(4) * delta.dot(gradient);
+            }
+        }
+
+        self.finish(value * self.variant.normalizer_4d())
+    }
+}
+impl NoiseSamplerState for SimplexSampler {
+    fn get_frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    fn get_seed(&self) -> u32 {
+        self.seed
+    }
+
+    fn get_period(&self) -> Option<u32> {
+        self.period
+    }
+
+    fn set_frequency(&mut self, new_frequency: f32) {
+        self.frequency = new_frequency;
+    }
+
+    fn set_seed(&mut self, new_seed: u32) {
+        self.seed = new_seed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every point of a grid of `resolution` per axis over the unit hypercube of `dimensions`.
+    fn grid(dimensions: usize, resolution: u32) -> impl Iterator<Item = glam::Vec4> {
+        (0..resolution.pow(dimensions as u32)).map(move |index| {
+            let axis = |axis: u32| (index / resolution.pow(axis) % resolution) as f32 / resolution as f32;
+            glam::vec4(axis(0), axis(1), axis(2), axis(3))
+        })
+    }
+
+    fn sample(sampler: &SimplexSampler, dimensions: usize, point: glam::Vec4) -> f32 {
+        match dimensions {
+            2 => sampler.sample_2d(point.xy()),
+            3 => sampler.sample_3d(point.xyz()),
+            _ => sampler.sample_4d(point),
+        }
+    }
+
+    #[test]
+    fn normalizers_fill_the_range_without_clipping() {
+        for variant in [SimplexVariant::Fast, SimplexVariant::Smooth] {
+            for (dimensions, resolution) in [(2, 128), (3, 24), (4, 10)] {
+                let (mut min, mut max) = (f32::MAX, f32::MIN);
+                for seed in 0..8 {
+                    let sampler = SimplexSampler::builder().frequency(4.0).seed(seed).variant(variant).build();
+                    for point in grid(dimensions, resolution) {
+                        let value = sample(&sampler, dimensions, point);
+                        min = min.min(value);
+                        max = max.max(value);
+                    }
+                }
+
+                // The sum is clamped, so a normalizer that is too large shows up as values at 0 or 1.
+                let name = format!("{variant:?} {dimensions}D");
+                assert!(min > 0.0 && max < 1.0, "{name} clipped, range {min}..{max}");
+                assert!(min < 0.1 && max > 0.9, "{name} only reached {min}..{max}");
+            }
+        }
+    }
+
+    #[test]
+    fn ridged_stays_in_range() {
+        for (dimensions, resolution) in [(2, 64), (3, 16), (4, 8)] {
+            let sampler = SimplexSampler::builder().frequency(3.0).seed(2).mode(SimplexMode::Ridged).build();
+            for point in grid(dimensions, resolution) {
+                let value = sample(&sampler, dimensions, point);
+                assert!((0.0..=1.0).contains(&value), "ridged {dimensions}D value {value} at {point}");
+            }
+        }
+    }
+
+    #[test]
+    fn tiles_at_whole_frequencies() {
+        let step = 1e-4;
+        for mode in [SimplexMode::Normal, SimplexMode::Ridged] {
+            for (frequency, period) in [(3.0, None), (6.0, Some(2))] {
+                let sampler = SimplexSampler::builder()
+                    .frequency(frequency)
+                    .maybe_period(period)
+                    .seed(4)
+                    .mode(mode)
+                    .build();
+
+                for dimensions in 2..=4 {
+                    for point in grid(dimensions, 3) {
+                        for axis in 0..dimensions {
+                            let inside = point + glam::Vec4::AXES[axis] * (1.0 - step - point[axis]);
+                            let outside = inside + glam::Vec4::AXES[axis] * 2.0 * step;
+                            let jump =
+                                (sample(&sampler, dimensions, inside) - sample(&sampler, dimensions, outside)).abs();
+                            assert!(
+                                jump < 1e-2,
+                                "{mode:?} {dimensions}D at frequency {frequency} jumps by {jump} along axis {axis}"
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
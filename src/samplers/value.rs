@@ -0,0 +1,226 @@
+use bon::Builder;
+use serde::{Deserialize, Serialize};
+
+use crate::random::hash::{pcg_33, pcg_44};
+use crate::samplers::{NoiseSampler, NoiseSamplerState, SampleParams, Smoothing};
+use crate::util::{mix, SmoothingFn};
+
+/// How [`ValueSampler`] blends between lattice values.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueInterpolation {
+    Linear,
+    /// `3t² - 2t³`, continuous first derivative.
+    CubicHermite,
+    /// `6t⁵ - 15t⁴ + 10t³`, continuous second derivative.
+    #[default]
+    Quintic,
+    /// A spline through the four nearest lattice values on each axis. It can overshoot, the result
+    /// is clamped to 0..1.
+    CatmullRom,
+}
+impl ValueInterpolation {
+    /// Lattice offsets read on each axis.
+    fn taps(&self) -> std::ops::RangeInclusive<i32> {
+        match self {
+            ValueInterpolation::CatmullRom => -1..=2,
+            _ => 0..=1,
+        }
+    }
+
+    /// Interpolates between `values[1]` and `values[2]`, `values[0]` and `values[3]` being their outer
+    /// neighbours.
+    fn interpolate(&self, values: [f32; 4], t: f32) -> f32 {
+        match self {
+            ValueInterpolation::Linear => mix(values[1], values[2], t),
+            ValueInterpolation::CubicHermite => mix(values[1], values[2], t.cubic_smooth()),
+            ValueInterpolation::Quintic => mix(values[1], values[2], t.quintic_smooth()),
+            ValueInterpolation::CatmullRom => {
+                let [p0, p1, p2, p3] = values;
+
+                0.5 * (2.0 * p1
+                    + (p2 - p0) * t
+                    + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t * t
+                    + (3.0 * (p1 - p2) + p3 - p0) * t * t * t)
+            }
+        }
+    }
+}
+
+/// Interpolated random values on the integer lattice, periodic at integer frequencies.
+#[derive(Debug, Builder)]
+pub struct ValueSampler {
+    pub frequency: f32,
+    #[builder(default = 0)]
+    pub seed: u32,
+    /// Lattice cells the noise repeats after at `frequency`, the frequency itself if unset.
+    pub period: Option<u32>,
+    #[builder(default = Smoothing::None)]
+    pub smoothing: Smoothing,
+    #[builder(default = ValueInterpolation::Quintic)]
+    pub interpolation: ValueInterpolation,
+}
+impl ValueSampler {
+    fn lattice_value_2d(&self, params: SampleParams, cell: glam::IVec2) -> f32 {
+        let cell = cell.rem_euclid(glam::IVec2::splat(params.period(self.period, self.frequency))).as_uvec2();
+        let hash = pcg_33(glam::uvec3(cell.x, cell.y, params.seed));
+
+        hash.x as f32 / u32::MAX as f32
+    }
+
+    fn lattice_value_3d(&self, params: SampleParams, cell: glam::IVec3) -> f32 {
+        let cell = cell.rem_euclid(glam::IVec3::splat(params.period(self.period, self.frequency))).as_uvec3();
+        let hash = pcg_44(glam::uvec4(cell.x, cell.y, cell.z, params.seed));
+
+        hash.x as f32 / u32::MAX as f32
+    }
+}
+impl NoiseSampler<f32> for ValueSampler {
+    fn sample_2d_with(&self, mut uv: glam::Vec2, params: SampleParams) -> f32 {
+        uv *= params.frequency;
+
+        let pi = uv.floor().as_ivec2();
+        let pf = uv - pi.as_vec2();
+
+        let mut rows = [0.0; 4];
+        for y in self.interpolation.taps() {
+            let mut row = [0.0; 4];
+            for x in self.interpolation.taps() {
+                row[(x + 1) as usize] = self.lattice_value_2d(params, pi + glam::ivec2(x, y));
+            }
+
+            rows[(y + 1) as usize] = self.interpolation.interpolate(row, pf.x);
+        }
+
+        let value = self.interpolation.interpolate(rows, pf.y).clamp(0.0, 1.0);
+        self.smoothing.smooth(value)
+    }
+
+    fn sample_3d_with(&self, mut uvw: glam::Vec3, params: SampleParams) -> f32 {
+        uvw *= params.frequency;
+
+        let pi = uvw.floor().as_ivec3();
+        let pf = uvw - pi.as_vec3();
+
+        let mut planes = [0.0; 4];
+        for z in self.interpolation.taps() {
+            let mut rows = [0.0; 4];
+            for y in self.interpolation.taps() {
+                let mut row = [0.0; 4];
+                for x in self.interpolation.taps() {
+                    row[(x + 1) as usize] = self.lattice_value_3d(params, pi + glam::ivec3(x, y, z));
+                }
+
+                rows[(y + 1) as usize] = self.interpolation.interpolate(row, pf.x);
+            }
+
+            planes[(z + 1) as usize] = self.interpolation.interpolate(rows, pf.y);
+        }
+
+        let value = self.interpolation.interpolate(planes, pf.z).clamp(0.0, 1.0);
+        self.smoothing.smooth(value)
+    }
+}
+impl NoiseSamplerState for ValueSampler {
+    fn get_frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    fn get_seed(&self) -> u32 {
+        self.seed
+    }
+
+    fn get_period(&self) -> Option<u32> {
+        self.period
+    }
+
+    fn set_frequency(&mut self, new_frequency: f32) {
+        self.frequency = new_frequency;
+    }
+
+    fn set_seed(&mut self, new_seed: u32) {
+        self.seed = new_seed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERPOLATIONS: [ValueInterpolation; 4] = [
+        ValueInterpolation::Linear,
+        ValueInterpolation::CubicHermite,
+        ValueInterpolation::Quintic,
+        ValueInterpolation::CatmullRom,
+    ];
+
+    fn sampler(interpolation: ValueInterpolation) -> ValueSampler {
+        ValueSampler::builder().frequency(4.0).seed(12).interpolation(interpolation).build()
+    }
+
+    #[test]
+    fn reproduces_lattice_values() {
+        for interpolation in INTERPOLATIONS {
+            let sampler = sampler(interpolation);
+            let params = sampler.params();
+
+            for x in 0..4 {
+                for y in 0..4 {
+                    let cell = glam::ivec3(x, y, (x + y) % 4);
+                    let value = sampler.sample_2d(cell.truncate().as_vec2() / 4.0);
+                    let expected = sampler.lattice_value_2d(params, cell.truncate());
+                    assert_eq!(value, expected, "{interpolation:?} 2D at {cell}");
+
+                    let value = sampler.sample_3d(cell.as_vec3() / 4.0);
+                    assert_eq!(value, sampler.lattice_value_3d(params, cell), "{interpolation:?} 3D at {cell}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stays_in_range() {
+        // Catmull-Rom overshoots between equal neighbours next to lower ones, which the sampler clamps.
+        assert!(ValueInterpolation::CatmullRom.interpolate([0.0, 1.0, 1.0, 0.0], 0.5) > 1.0);
+
+        for interpolation in INTERPOLATIONS {
+            let sampler = sampler(interpolation);
+            for index in 0..4096 {
+                let uvw = glam::uvec3(index % 64, index / 64, index * 29 % 64).as_vec3() / 64.0;
+                for value in [sampler.sample_2d(uvw.truncate()), sampler.sample_3d(uvw)] {
+                    assert!((0.0..=1.0).contains(&value), "{interpolation:?} value {value} at {uvw}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tiles_at_whole_frequencies() {
+        let step = 1e-4;
+        for interpolation in INTERPOLATIONS {
+            for (frequency, period) in [(3.0, None), (8.0, Some(2))] {
+                let sampler = ValueSampler::builder()
+                    .frequency(frequency)
+                    .maybe_period(period)
+                    .seed(4)
+                    .interpolation(interpolation)
+                    .build();
+
+                for t in [0.15, 0.5, 0.85] {
+                    for axis in 0..3 {
+                        let inside = glam::Vec3::splat(t) + glam::Vec3::AXES[axis] * (1.0 - t - step);
+                        let outside = inside + glam::Vec3::AXES[axis] * 2.0 * step;
+                        let jump = (sampler.sample_3d(inside) - sampler.sample_3d(outside)).abs();
+                        assert!(jump < 1e-2, "{interpolation:?} 3D jumps by {jump} along axis {axis}");
+
+                        if axis < 2 {
+                            let (inside, outside) = (inside.truncate(), outside.truncate());
+                            let jump = (sampler.sample_2d(inside) - sampler.sample_2d(outside)).abs();
+                            assert!(jump < 1e-2, "{interpolation:?} 2D jumps by {jump} along axis {axis}");
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,34 +1,50 @@
 use bon::Builder;
-use glam::Vec4Swizzles;
+use glam::{Vec3Swizzles, Vec4Swizzles};
 
-use crate::hash::{hash_ivec3, pcg_1d, rand_unit_vector_3d, rand_vector_3d};
-use crate::random::hash::{pcg_11, pcg_44};
+use crate::random::hash::{pcg_11, pcg_33, pcg_44, pcg_54};
 use crate::random::unit::unit_vector_23;
-use crate::samplers::{NoiseSampler, NoiseSamplerState, Smoothing};
-use crate::util::{clamp, clamp_vec3, mix, mix_vec3, SmoothingFn};
+use crate::samplers::{GradientSampler, NoiseSampler, NoiseSamplerState, SampleParams, Smoothing};
+use crate::util::{corner_4d, mix_corners_4d, mix_vec3, CoordWrapping, SmoothingFn};
 
+/// Random unit vectors on the integer lattice, interpolated between cells.
+///
+/// In 2D the lattice is a grid but the vectors are still 3D, so the z component can serve as the
+/// potential of a 2D [`CurlSampler`].
 #[derive(Debug, Builder)]
 pub struct VectorFieldSampler {
     pub frequency: f32,
     #[builder(default = 0)]
     pub seed: u32,
+    /// Lattice cells the noise repeats after at `frequency`, the frequency itself if unset.
+    pub period: Option<u32>,
     #[builder(default = Smoothing::None)]
     pub smoothing: Smoothing,
     pub bias: Option<glam::Vec3>
 }
 impl VectorFieldSampler {
+    fn get_random_direction_2d(&self, params: SampleParams, pi: glam::IVec2, offset: glam::IVec2) -> glam::Vec3 {
+        let cell = (pi + offset).rem_euclid(glam::IVec2::splat(params.period(self.period, self.frequency)));
+        let hash = pcg_33(glam::uvec3(cell.x.cast_unsigned(), cell.y.cast_unsigned(), params.seed));
+
+        let unit_vector = unit_vector_23(hash.xy());
+        match &self.bias {
+            Some(bias) => (unit_vector + bias).normalize(),
+            None => unit_vector,
+        }
+    }
+
     fn get_random_direction_3d(
         &self,
+        params: SampleParams,
         pi: glam::IVec3,
-        pf: glam::Vec3,
         offset: glam::IVec3,
     ) -> glam::Vec3 {
-        let cell = (pi + offset).rem_euclid(glam::IVec3::splat(self.frequency as i32));
+        let cell = (pi + offset).rem_euclid(glam::IVec3::splat(params.period(self.period, self.frequency)));
         let hash = pcg_44(glam::uvec4(
             cell.x.cast_unsigned(),
             cell.y.cast_unsigned(),
             cell.z.cast_unsigned(),
-            self.seed,
+            params.seed,
         ));
 
         let unit_vector = unit_vector_23(hash.xy());
@@ -37,28 +53,59 @@ impl VectorFieldSampler {
             None => unit_vector,
         }
     }
+
+    fn get_random_direction_4d(
+        &self,
+        params: SampleParams,
+        pi: glam::IVec4,
+        offset: glam::IVec4,
+    ) -> glam::Vec3 {
+        let cell = (pi + offset).rem_euclid(glam::IVec4::splat(params.period(self.period, self.frequency)));
+        let hash = pcg_54(cell.as_uvec4(), params.seed);
+
+        let unit_vector = unit_vector_23(hash.xy());
+        match &self.bias {
+            Some(bias) => (unit_vector + bias).normalize(),
+            None => unit_vector,
+        }
+    }
 }
 impl NoiseSampler<glam::Vec3> for VectorFieldSampler {
-    fn sample_2d(&mut self, _uv: glam::Vec2) -> glam::Vec3 {
-        panic!("2D vector fields are currently not supported")
+    fn sample_2d_with(&self, mut uv: glam::Vec2, params: SampleParams) -> glam::Vec3 {
+        uv *= params.frequency;
+
+        let pi = uv.floor().as_ivec2();
+        let pf = uv - pi.as_vec2();
+
+        let f = pf.quintic_smooth();
+
+        let d00 = self.get_random_direction_2d(params, pi, glam::ivec2(0, 0));
+        let d10 = self.get_random_direction_2d(params, pi, glam::ivec2(1, 0));
+        let d01 = self.get_random_direction_2d(params, pi, glam::ivec2(0, 1));
+        let d11 = self.get_random_direction_2d(params, pi, glam::ivec2(1, 1));
+
+        let x0 = mix_vec3(d00, d10, f.x);
+        let x1 = mix_vec3(d01, d11, f.x);
+
+        mix_vec3(x0, x1, f.y)
     }
 
-    fn sample_3d(&mut self, mut uvw: glam::Vec3) -> glam::Vec3 {
-        uvw *= self.frequency;
+    fn sample_3d_with(&self, mut uvw: glam::Vec3, params: SampleParams) -> glam::Vec3 {
+        uvw *= params.frequency;
 
         let pi = uvw.floor().as_ivec3();
-        let pf = uvw.fract();
+        let pf = uvw.fract_gl();
 
         let f = pf.quintic_smooth();
 
-        let d000 = self.get_random_direction_3d(pi, pf, glam::ivec3(0, 0, 0));
-        let d001 = self.get_random_direction_3d(pi, pf, glam::ivec3(0, 0, 1));
-        let d010 = self.get_random_direction_3d(pi, pf, glam::ivec3(0, 1, 0));
-        let d011 = self.get_random_direction_3d(pi, pf, glam::ivec3(0, 1, 1));
-        let d100 = self.get_random_direction_3d(pi, pf, glam::ivec3(1, 0, 0));
-        let d101 = self.get_random_direction_3d(pi, pf, glam::ivec3(1, 0, 1));
-        let d110 = self.get_random_direction_3d(pi, pf, glam::ivec3(1, 1, 0));
-        let d111 = self.get_random_direction_3d(pi, pf, glam::ivec3(1, 1, 1));
+        let d000 = self.get_random_direction_3d(params, pi, glam::ivec3(0, 0, 0));
+        let d001 = self.get_random_direction_3d(params, pi, glam::ivec3(0, 0, 1));
+        let d010 = self.get_random_direction_3d(params, pi, glam::ivec3(0, 1, 0));
+        let d011 = self.get_random_direction_3d(params, pi, glam::ivec3(0, 1, 1));
+        let d100 = self.get_random_direction_3d(params, pi, glam::ivec3(1, 0, 0));
+        let d101 = self.get_random_direction_3d(params, pi, glam::ivec3(1, 0, 1));
+        let d110 = self.get_random_direction_3d(params, pi, glam::ivec3(1, 1, 0));
+        let d111 = self.get_random_direction_3d(params, pi, glam::ivec3(1, 1, 1));
 
         let x00 = mix_vec3(d000, d100, f.x);
         let x01 = mix_vec3(d001, d101, f.x);
@@ -70,6 +117,16 @@ impl NoiseSampler<glam::Vec3> for VectorFieldSampler {
 
         mix_vec3(y0, y1, f.z)
     }
+
+    fn sample_4d_with(&self, mut uvwt: glam::Vec4, params: SampleParams) -> glam::Vec3 {
+        uvwt *= params.frequency;
+
+        let pi = uvwt.floor().as_ivec4();
+        let pf = uvwt - pi.as_vec4();
+
+        let corners = std::array::from_fn(|index| self.get_random_direction_4d(params, pi, corner_4d(index)));
+        mix_corners_4d(corners, pf.quintic_smooth())
+    }
 }
 impl NoiseSamplerState for VectorFieldSampler {
     fn get_frequency(&self) -> f32 {
@@ -80,6 +137,10 @@ impl NoiseSamplerState for VectorFieldSampler {
         self.seed
     }
 
+    fn get_period(&self) -> Option<u32> {
+        self.period
+    }
+
     fn set_frequency(&mut self, new_frequency: f32) {
         self.frequency = new_frequency;
     }
@@ -99,35 +160,69 @@ pub struct VectorFieldFbmSampler<S: NoiseSampler<glam::Vec3>> {
     lacunarity: f32,
     #[builder(default = Smoothing::None)]
     smoothing: Smoothing,
-    #[builder(default = false)]
-    restore_original_state: bool,
 }
 impl<S: NoiseSampler<glam::Vec3>> NoiseSampler<glam::Vec3> for VectorFieldFbmSampler<S> {
-    fn sample_2d(&mut self, uv: glam::Vec2) -> glam::Vec3 {
-        panic!("2D vector fields are currently not supported")
-    }
+    fn sample_2d_with(&self, uv: glam::Vec2, mut params: SampleParams) -> glam::Vec3 {
+        let mut noise_sum: glam::Vec3 = glam::Vec3::ZERO;
+
+        for octave in 0..self.octaves {
+            let sample = self.sampler.sample_2d_with(uv, params);
+            let amplitude = (1.0 / self.persistence).powf(octave as f32);
 
-    fn sample_3d(&mut self, uvw: glam::Vec3) -> glam::Vec3 {
-        let backup_frequency = self.sampler.get_frequency();
-        let backup_seed = self.sampler.get_seed();
+            noise_sum += sample * amplitude;
+
+            params.frequency *= self.lacunarity;
+            params.seed = pcg_11(params.seed);
+        }
 
+        self.smoothing.smooth(noise_sum)
+    }
+
+    fn sample_3d_with(&self, uvw: glam::Vec3, mut params: SampleParams) -> glam::Vec3 {
         let mut noise_sum: glam::Vec3 = glam::Vec3::ZERO;
         let mut amplitude_sum: glam::Vec3 = glam::Vec3::ZERO;
 
         for octave in 0..self.octaves {
-            let sample = self.sampler.sample_3d(uvw);
+            let sample = self.sampler.sample_3d_with(uvw, params);
             let amplitude = (1.0 / self.persistence).powf(octave as f32);
 
             noise_sum += sample * amplitude;
             amplitude_sum += amplitude;
 
-            self.sampler.set_frequency(self.sampler.get_frequency() * self.lacunarity);
-            self.sampler.set_seed(pcg_11(self.sampler.get_seed()));
+            params.frequency *= self.lacunarity;
+            params.seed = pcg_11(params.seed);
         }
 
-        if self.restore_original_state {
-            self.sampler.set_frequency(backup_frequency);
-            self.sampler.set_seed(backup_seed);
+        self.smoothing.smooth(noise_sum)
+    }
+
+    fn sample_4d_with(&self, uvwt: glam::Vec4, mut params: SampleParams) -> glam::Vec3 {
+        let mut noise_sum: glam::Vec3 = glam::Vec3::ZERO;
+
+        for octave in 0..self.octaves {
+            let sample = self.sampler.sample_4d_with(uvwt, params);
+            let amplitude = (1.0 / self.persistence).powf(octave as f32);
+
+            noise_sum += sample * amplitude;
+
+            params.frequency *= self.lacunarity;
+            params.seed = pcg_11(params.seed);
+        }
+
+        self.smoothing.smooth(noise_sum)
+    }
+
+    fn sample_2d_time_with(&self, uvt: glam::Vec3, mut params: SampleParams) -> glam::Vec3 {
+        let mut noise_sum: glam::Vec3 = glam::Vec3::ZERO;
+
+        for octave in 0..self.octaves {
+            let sample = self.sampler.sample_2d_time_with(uvt, params);
+            let amplitude = (1.0 / self.persistence).powf(octave as f32);
+
+            noise_sum += sample * amplitude;
+
+            params.frequency *= self.lacunarity;
+            params.seed = pcg_11(params.seed);
         }
 
         self.smoothing.smooth(noise_sum)
@@ -142,6 +237,10 @@ impl<S: NoiseSampler<glam::Vec3>> NoiseSamplerState for VectorFieldFbmSampler<S>
         self.sampler.get_seed()
     }
 
+    fn get_period(&self) -> Option<u32> {
+        self.sampler.get_period()
+    }
+
     fn set_frequency(&mut self, new_frequency: f32) {
         self.sampler.set_frequency(new_frequency)
     }
@@ -151,6 +250,11 @@ impl<S: NoiseSampler<glam::Vec3>> NoiseSamplerState for VectorFieldFbmSampler<S>
     }
 }
 
+/// The curl of a vector field, a divergence-free field for swirling, incompressible motion.
+///
+/// Derivatives are taken by central differences on a grid of `size` cells, wrapping around so the
+/// curl tiles like the field does. In 2D the field's z component is the potential, giving the
+/// in-plane curl `(dz/dy, -dz/dx, 0)`.
 #[derive(Debug, Builder)]
 pub struct CurlSampler<S: NoiseSampler<glam::Vec3>> {
     sampler: S,
@@ -160,14 +264,25 @@ impl<S: NoiseSampler<glam::Vec3>> CurlSampler<S> {
     fn wrap_uvw_3d(&self, uvw: glam::Vec3) -> glam::Vec3 {
         ((uvw - 0.5) * self.size).rem_euclid(self.size) / self.size
     }
-}
-impl<S: NoiseSampler<glam::Vec3>> NoiseSampler<glam::Vec3> for CurlSampler<S> {
-    fn sample_2d(&mut self, uv: glam::Vec2) -> glam::Vec3 {
-        todo!()
+
+    fn curl_2d(&self, uv: glam::Vec2, params: SampleParams, sample: impl Fn(glam::Vec2) -> glam::Vec3) -> glam::Vec3 {
+        let size = self.size.xy() * params.frequency;
+        let delta = 1.0 / size;
+        let span = delta * size * 2.0;
+
+        let delta_x = glam::Vec2::X * delta;
+        let delta_y = glam::Vec2::Y * delta;
+
+        let dx = (sample((uv + delta_x).wrap_coord(self.size.xy())) - sample((uv - delta_x).wrap_coord(self.size.xy())))
+            / span.x;
+        let dy = (sample((uv + delta_y).wrap_coord(self.size.xy())) - sample((uv - delta_y).wrap_coord(self.size.xy())))
+            / span.y;
+
+        glam::vec3(dy.z, -dx.z, 0.0)
     }
 
-    fn sample_3d(&mut self, uvw: glam::Vec3) -> glam::Vec3 {
-        let size = self.size * self.sampler.get_frequency();
+    fn curl_3d(&self, uvw: glam::Vec3, params: SampleParams, sample: impl Fn(glam::Vec3) -> glam::Vec3) -> glam::Vec3 {
+        let size = self.size * params.frequency;
         let delta = 1.0 / size;
         let span = delta * size * 2.0;
 
@@ -175,23 +290,37 @@ impl<S: NoiseSampler<glam::Vec3>> NoiseSampler<glam::Vec3> for CurlSampler<S> {
         let delta_y = glam::Vec3::Y * delta;
         let delta_z = glam::Vec3::Z * delta;
 
-        let dx = (self.sampler.sample_3d(self.wrap_uvw_3d(uvw + delta_x))
-            - self.sampler.sample_3d(self.wrap_uvw_3d(uvw - delta_x)))
-            / span;
-
-        let dy = (self.sampler.sample_3d(self.wrap_uvw_3d(uvw + delta_y))
-            - self.sampler.sample_3d(self.wrap_uvw_3d(uvw - delta_y)))
-            / span;
-
-        let dz = (self.sampler.sample_3d(self.wrap_uvw_3d(uvw + delta_z))
-            - self.sampler.sample_3d(self.wrap_uvw_3d(uvw - delta_z)))
-            / span;
+        let dx = (sample(self.wrap_uvw_3d(uvw + delta_x)) - sample(self.wrap_uvw_3d(uvw - delta_x))) / span;
+        let dy = (sample(self.wrap_uvw_3d(uvw + delta_y)) - sample(self.wrap_uvw_3d(uvw - delta_y))) / span;
+        let dz = (sample(self.wrap_uvw_3d(uvw + delta_z)) - sample(self.wrap_uvw_3d(uvw - delta_z))) / span;
 
         (dy.z - dz.y) * glam::Vec3::X
             + (dz.x - dx.z) * glam::Vec3::Y
             + (dx.y - dy.x) * glam::Vec3::Z
     }
 }
+impl<S: NoiseSampler<glam::Vec3>> NoiseSampler<glam::Vec3> for CurlSampler<S> {
+    fn sample_2d_with(&self, uv: glam::Vec2, params: SampleParams) -> glam::Vec3 {
+        self.curl_2d(uv, params, |uv| self.sampler.sample_2d_with(uv, params))
+    }
+
+    fn sample_3d_with(&self, uvw: glam::Vec3, params: SampleParams) -> glam::Vec3 {
+        self.curl_3d(uvw, params, |uvw| self.sampler.sample_3d_with(uvw, params))
+    }
+
+    /// The curl of the field as it is at time `uvwt.w`, differentiated in space only.
+    fn sample_4d_with(&self, uvwt: glam::Vec4, params: SampleParams) -> glam::Vec3 {
+        self.curl_3d(uvwt.truncate(), params, |uvw| {
+            self.sampler.sample_4d_with(uvw.extend(uvwt.w), params)
+        })
+    }
+
+    fn sample_2d_time_with(&self, uvt: glam::Vec3, params: SampleParams) -> glam::Vec3 {
+        self.curl_2d(uvt.xy(), params, |uv| {
+            self.sampler.sample_2d_time_with(uv.extend(uvt.z), params)
+        })
+    }
+}
 impl<S: NoiseSampler<glam::Vec3>> NoiseSamplerState for CurlSampler<S> {
     fn get_frequency(&self) -> f32 {
         self.sampler.get_frequency()
@@ -201,6 +330,10 @@ impl<S: NoiseSampler<glam::Vec3>> NoiseSamplerState for CurlSampler<S> {
         self.sampler.get_seed()
     }
 
+    fn get_period(&self) -> Option<u32> {
+        self.sampler.get_period()
+    }
+
     fn set_frequency(&mut self, new_frequency: f32) {
         self.sampler.set_frequency(new_frequency);
     }
@@ -209,3 +342,88 @@ impl<S: NoiseSampler<glam::Vec3>> NoiseSamplerState for CurlSampler<S> {
         self.sampler.set_seed(new_seed);
     }
 }
+
+/// Offsets the seed of the second and third potentials of a [`GradientCurlSampler`].
+const POTENTIAL_SEED_STEP: u32 = 0x9E37_79B9;
+
+/// Curl noise from the analytic gradients of scalar potentials, after Bridson et al.
+///
+/// In 3D the curl of the vector potential `(p1, p2, p3)` is taken from three copies of `potential`
+/// with different seeds, in 2D the single potential gives `(dp/dy, -dp/dx, 0)`. The gradients are
+/// exact, so unlike [`CurlSampler`] there is no differentiation grid, and each pixel costs three
+/// evaluations of the potential instead of six of a vector field. The curl is measured in cells of
+/// the potential's base frequency, which keeps it around -1..1 whatever the frequency.
+#[derive(Debug, Builder)]
+pub struct GradientCurlSampler<S: GradientSampler> {
+    potential: S,
+}
+impl<S: GradientSampler> GradientCurlSampler<S> {
+    fn potential_params(params: SampleParams, index: u32) -> SampleParams {
+        SampleParams {
+            frequency: params.frequency,
+            seed: params.seed.wrapping_add(index.wrapping_mul(POTENTIAL_SEED_STEP)),
+        }
+    }
+}
+impl<S: GradientSampler> NoiseSampler<glam::Vec3> for GradientCurlSampler<S> {
+    fn sample_2d_with(&self, uv: glam::Vec2, params: SampleParams) -> glam::Vec3 {
+        let (_, gradient) = self.potential.sample_2d_gradient_with(uv, params);
+        let gradient = gradient / params.frequency;
+
+        glam::vec3(gradient.y, -gradient.x, 0.0)
+    }
+
+    fn sample_3d_with(&self, uvw: glam::Vec3, params: SampleParams) -> glam::Vec3 {
+        let [d1, d2, d3] = [0, 1, 2].map(|index| {
+            let (_, gradient) = self
+                .potential
+                .sample_3d_gradient_with(uvw, Self::potential_params(params, index));
+
+            gradient / params.frequency
+        });
+
+        glam::vec3(d3.y - d2.z, d1.z - d3.x, d2.x - d1.y)
+    }
+
+    /// The curl of the potentials as they are at time `uvwt.w`, their time derivatives dropped.
+    fn sample_4d_with(&self, uvwt: glam::Vec4, params: SampleParams) -> glam::Vec3 {
+        let [d1, d2, d3] = [0, 1, 2].map(|index| {
+            let (_, gradient) = self
+                .potential
+                .sample_4d_gradient_with(uvwt, Self::potential_params(params, index));
+
+            gradient.truncate() / params.frequency
+        });
+
+        glam::vec3(d3.y - d2.z, d1.z - d3.x, d2.x - d1.y)
+    }
+
+    /// The potential is sampled in 3D with time as its third axis, keeping the in-plane gradient.
+    fn sample_2d_time_with(&self, uvt: glam::Vec3, params: SampleParams) -> glam::Vec3 {
+        let (_, gradient) = self.potential.sample_3d_gradient_with(uvt, params);
+        let gradient = gradient / params.frequency;
+
+        glam::vec3(gradient.y, -gradient.x, 0.0)
+    }
+}
+impl<S: GradientSampler> NoiseSamplerState for GradientCurlSampler<S> {
+    fn get_frequency(&self) -> f32 {
+        self.potential.get_frequency()
+    }
+
+    fn get_seed(&self) -> u32 {
+        self.potential.get_seed()
+    }
+
+    fn get_period(&self) -> Option<u32> {
+        self.potential.get_period()
+    }
+
+    fn set_frequency(&mut self, new_frequency: f32) {
+        self.potential.set_frequency(new_frequency);
+    }
+
+    fn set_seed(&mut self, new_seed: u32) {
+        self.potential.set_seed(new_seed);
+    }
+}
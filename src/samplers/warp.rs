@@ -0,0 +1,282 @@
+use bon::Builder;
+use glam::Vec3Swizzles;
+use serde::{Deserialize, Serialize};
+
+use crate::samplers::{NoiseSampler, NoiseSamplerState, SampleParams};
+use crate::util::CoordWrapping;
+
+/// Samples `sampler` at coordinates offset by the vector sampler `warp`.
+///
+/// With more than one iteration the warp is fed its own output, `p + warp(p + warp(p))` for two. The
+/// warped coordinates are wrapped back into the texture at `size`, so the result tiles as long as
+/// both samplers do.
+#[derive(Debug, Builder)]
+pub struct DomainWarpSampler<S: NoiseSampler<f32>, W: NoiseSampler<glam::Vec3>> {
+    sampler: S,
+    warp: W,
+    size: glam::Vec3,
+    #[builder(default = 1.0)]
+    strength: f32,
+    #[builder(default = 1)]
+    iterations: u32,
+}
+impl<S: NoiseSampler<f32>, W: NoiseSampler<glam::Vec3>> NoiseSampler<f32> for DomainWarpSampler<S, W> {
+    fn sample_2d_with(&self, uv: glam::Vec2, params: SampleParams) -> f32 {
+        let warp_params = params.rebase(self.params(), self.warp.params());
+
+        let mut warped = uv;
+        for _ in 0..self.iterations {
+            let offset = self.warp.sample_2d_with(warped, warp_params).xy() * self.strength;
+            warped = (uv + offset).wrap_coord(self.size.xy());
+        }
+
+        self.sampler.sample_2d_with(warped, params)
+    }
+
+    fn sample_3d_with(&self, uvw: glam::Vec3, params: SampleParams) -> f32 {
+        let warp_params = params.rebase(self.params(), self.warp.params());
+
+        let mut warped = uvw;
+        for _ in 0..self.iterations {
+            let offset = self.warp.sample_3d_with(warped, warp_params) * self.strength;
+            warped = (uvw + offset).wrap_coord(self.size);
+        }
+
+        self.sampler.sample_3d_with(warped, params)
+    }
+
+    /// Only the spatial coordinates are warped, time passes through unchanged.
+    fn sample_4d_with(&self, uvwt: glam::Vec4, params: SampleParams) -> f32 {
+        let warp_params = params.rebase(self.params(), self.warp.params());
+
+        let mut warped = uvwt;
+        for _ in 0..self.iterations {
+            let offset = self.warp.sample_4d_with(warped, warp_params) * self.strength;
+            warped = (uvwt.truncate() + offset).wrap_coord(self.size).extend(uvwt.w);
+        }
+
+        self.sampler.sample_4d_with(warped, params)
+    }
+
+    fn sample_2d_time_with(&self, uvt: glam::Vec3, params: SampleParams) -> f32 {
+        let warp_params = params.rebase(self.params(), self.warp.params());
+
+        let mut warped = uvt;
+        for _ in 0..self.iterations {
+            let offset = self.warp.sample_2d_time_with(warped, warp_params).xy() * self.strength;
+            warped = (uvt.xy() + offset).wrap_coord(self.size.xy()).extend(uvt.z);
+        }
+
+        self.sampler.sample_2d_time_with(warped, params)
+    }
+}
+impl<S: NoiseSampler<f32>, W: NoiseSampler<glam::Vec3>> NoiseSamplerState for DomainWarpSampler<S, W> {
+    fn get_frequency(&self) -> f32 {
+        self.sampler.get_frequency()
+    }
+
+    fn get_seed(&self) -> u32 {
+        self.sampler.get_seed()
+    }
+
+    fn get_period(&self) -> Option<u32> {
+        self.sampler.get_period()
+    }
+
+    fn set_frequency(&mut self, new_frequency: f32) {
+        let new_params = SampleParams {
+            frequency: new_frequency,
+            seed: self.get_seed(),
+        };
+
+        self.warp.set_frequency(new_params.rebase(self.params(), self.warp.params()).frequency);
+        self.sampler.set_frequency(new_frequency);
+    }
+
+    fn set_seed(&mut self, new_seed: u32) {
+        let new_params = SampleParams {
+            frequency: self.get_frequency(),
+            seed: new_seed,
+        };
+
+        self.warp.set_seed(new_params.rebase(self.params(), self.warp.params()).seed);
+        self.sampler.set_seed(new_seed);
+    }
+}
+
+/// Builds a vector from three scalar samplers, each mapped from 0..1 to -1..1.
+///
+/// The frequency and seed are those of `x`, the other two follow it like the inputs of a
+/// [`CombineSampler`](crate::samplers::combine::CombineSampler).
+#[derive(Debug, Builder)]
+pub struct VectorFromScalarsSampler<X: NoiseSampler<f32>, Y: NoiseSampler<f32>, Z: NoiseSampler<f32>> {
+    x: X,
+    y: Y,
+    z: Z,
+}
+impl<X: NoiseSampler<f32>, Y: NoiseSampler<f32>, Z: NoiseSampler<f32>> NoiseSampler<glam::Vec3>
+    for VectorFromScalarsSampler<X, Y, Z>
+{
+    fn sample_2d_with(&self, uv: glam::Vec2, params: SampleParams) -> glam::Vec3 {
+        let base = self.params();
+
+        glam::vec3(
+            self.x.sample_2d_with(uv, params),
+            self.y.sample_2d_with(uv, params.rebase(base, self.y.params())),
+            self.z.sample_2d_with(uv, params.rebase(base, self.z.params())),
+        ) * 2.0
+            - 1.0
+    }
+
+    fn sample_3d_with(&self, uvw: glam::Vec3, params: SampleParams) -> glam::Vec3 {
+        let base = self.params();
+
+        glam::vec3(
+            self.x.sample_3d_with(uvw, params),
+            self.y.sample_3d_with(uvw, params.rebase(base, self.y.params())),
+            self.z.sample_3d_with(uvw, params.rebase(base, self.z.params())),
+        ) * 2.0
+            - 1.0
+    }
+
+    fn sample_4d_with(&self, uvwt: glam::Vec4, params: SampleParams) -> glam::Vec3 {
+        let base = self.params();
+
+        glam::vec3(
+            self.x.sample_4d_with(uvwt, params),
+            self.y.sample_4d_with(uvwt, params.rebase(base, self.y.params())),
+            self.z.sample_4d_with(uvwt, params.rebase(base, self.z.params())),
+        ) * 2.0
+            - 1.0
+    }
+
+    fn sample_2d_time_with(&self, uvt: glam::Vec3, params: SampleParams) -> glam::Vec3 {
+        let base = self.params();
+
+        glam::vec3(
+            self.x.sample_2d_time_with(uvt, params),
+            self.y.sample_2d_time_with(uvt, params.rebase(base, self.y.params())),
+            self.z.sample_2d_time_with(uvt, params.rebase(base, self.z.params())),
+        ) * 2.0
+            - 1.0
+    }
+}
+impl<X: NoiseSampler<f32>, Y: NoiseSampler<f32>, Z: NoiseSampler<f32>> NoiseSamplerState
+    for VectorFromScalarsSampler<X, Y, Z>
+{
+    fn get_frequency(&self) -> f32 {
+        self.x.get_frequency()
+    }
+
+    fn get_seed(&self) -> u32 {
+        self.x.get_seed()
+    }
+
+    fn get_period(&self) -> Option<u32> {
+        self.x.get_period()
+    }
+
+    fn set_frequency(&mut self, new_frequency: f32) {
+        let new_params = SampleParams {
+            frequency: new_frequency,
+            seed: self.get_seed(),
+        };
+        let base = self.params();
+
+        self.y.set_frequency(new_params.rebase(base, self.y.params()).frequency);
+        self.z.set_frequency(new_params.rebase(base, self.z.params()).frequency);
+        self.x.set_frequency(new_frequency);
+    }
+
+    fn set_seed(&mut self, new_seed: u32) {
+        let new_params = SampleParams {
+            frequency: self.get_frequency(),
+            seed: new_seed,
+        };
+        let base = self.params();
+
+        self.y.set_seed(new_params.rebase(base, self.y.params()).seed);
+        self.z.set_seed(new_params.rebase(base, self.z.params()).seed);
+        self.x.set_seed(new_seed);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+impl Axis {
+    pub fn index(&self) -> usize {
+        match self {
+            Axis::X => 0,
+            Axis::Y => 1,
+            Axis::Z => 2,
+        }
+    }
+}
+
+/// Reads one component of a vector sampler, scaled by `scale` and mapped from -1..1 to 0..1.
+///
+/// The inverse of [`VectorFromScalarsSampler`], used to write vector fields such as curl noise to
+/// the channels of a texture. The result is clamped, so `scale` should bring the field within -1..1.
+/// A `signed` component is only scaled, for float textures that keep the sign.
+#[derive(Debug, Builder)]
+pub struct VectorComponentSampler<V: NoiseSampler<glam::Vec3>> {
+    sampler: V,
+    axis: Axis,
+    #[builder(default = 1.0)]
+    scale: f32,
+    #[builder(default = false)]
+    signed: bool,
+}
+impl<V: NoiseSampler<glam::Vec3>> VectorComponentSampler<V> {
+    fn component(&self, vector: glam::Vec3) -> f32 {
+        let component = vector[self.axis.index()] * self.scale;
+        if self.signed {
+            component
+        } else {
+            (component * 0.5 + 0.5).clamp(0.0, 1.0)
+        }
+    }
+}
+impl<V: NoiseSampler<glam::Vec3>> NoiseSampler<f32> for VectorComponentSampler<V> {
+    fn sample_2d_with(&self, uv: glam::Vec2, params: SampleParams) -> f32 {
+        self.component(self.sampler.sample_2d_with(uv, params))
+    }
+
+    fn sample_3d_with(&self, uvw: glam::Vec3, params: SampleParams) -> f32 {
+        self.component(self.sampler.sample_3d_with(uvw, params))
+    }
+
+    fn sample_4d_with(&self, uvwt: glam::Vec4, params: SampleParams) -> f32 {
+        self.component(self.sampler.sample_4d_with(uvwt, params))
+    }
+
+    fn sample_2d_time_with(&self, uvt: glam::Vec3, params: SampleParams) -> f32 {
+        self.component(self.sampler.sample_2d_time_with(uvt, params))
+    }
+}
+impl<V: NoiseSampler<glam::Vec3>> NoiseSamplerState for VectorComponentSampler<V> {
+    fn get_frequency(&self) -> f32 {
+        self.sampler.get_frequency()
+    }
+
+    fn get_seed(&self) -> u32 {
+        self.sampler.get_seed()
+    }
+
+    fn get_period(&self) -> Option<u32> {
+        self.sampler.get_period()
+    }
+
+    fn set_frequency(&mut self, new_frequency: f32) {
+        self.sampler.set_frequency(new_frequency)
+    }
+
+    fn set_seed(&mut self, new_seed: u32) {
+        self.sampler.set_seed(new_seed)
+    }
+}
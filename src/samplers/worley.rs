@@ -1,110 +1,206 @@
 use bon::Builder;
-use glam::{Vec3Swizzles, Vec4Swizzles};
+use serde::{Deserialize, Serialize};
 
-use crate::hash::{hash_ivec2, hash_ivec3, pcg_1d, rand_unit_vector_2d, rand_unit_vector_3d, rand_vector_2d, rand_vector_3d};
-use crate::random::hash::{pcg_33, pcg_44};
-use crate::samplers::{NoiseSampler, NoiseSamplerState, Smoothing};
-use crate::util::{SmoothingFn, clamp, cubic_smooth};
+use crate::samplers::cell::{cell_points_2d, cell_points_3d, cell_points_4d};
+use crate::samplers::{NoiseSampler, NoiseSamplerState, SampleParams, Smoothing};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum WorleyMode {
     F1,
     F2,
+    F3,
+    F4,
+    #[default]
     OneMinusF1,
     OneMinusF2,
     F2MinusF1,
     F1MinusF2,
+    /// The random value of the closest point, so one per cell unless `points_per_cell` is set.
+    CellValue,
 }
 
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorleyMetric {
+    #[default]
+    Euclidean,
+    EuclideanSquared,
+    Manhattan,
+    Chebyshev,
+    /// Generalizes Manhattan (1) and Euclidean (2) distances to any exponent.
+    Minkowski(f32),
+}
+impl WorleyMetric {
+    /// The length of a vector with the components of `delta`, in any number of dimensions.
+    pub fn distance(&self, delta: impl IntoIterator<Item = f32>) -> f32 {
+        let components = delta.into_iter().map(f32::abs);
+
+        match self {
+            WorleyMetric::Euclidean => components.map(|component| component * component).sum::<f32>().sqrt(),
+            WorleyMetric::EuclideanSquared => components.map(|component| component * component).sum(),
+            WorleyMetric::Manhattan => components.sum(),
+            WorleyMetric::Chebyshev => components.fold(0.0, f32::max),
+            WorleyMetric::Minkowski(exponent) => components
+                .map(|component| component.powf(*exponent))
+                .sum::<f32>()
+                .powf(exponent.recip()),
+        }
+    }
+}
+
+/// The four closest distances, in order, and the random value of the closest point's cell.
+#[derive(Debug, Clone, Copy)]
+struct Features {
+    distances: [f32; 4],
+    cell_value: f32,
+}
+impl Features {
+    fn new() -> Self {
+        Self {
+            distances: [f32::MAX; 4],
+            cell_value: 0.0,
+        }
+    }
+
+    fn insert(&mut self, dist: f32, cell_value: f32) {
+        let Some(index) = self.distances.iter().position(|closest| dist < *closest) else {
+            return;
+        };
+
+        self.distances.copy_within(index..3, index + 1);
+        self.distances[index] = dist;
+
+        if index == 0 {
+            self.cell_value = cell_value;
+        }
+    }
+}
+
+/// Cellular noise from random points in each lattice cell.
+///
+/// Points are searched `radius` cells around the sampled one. The default of 1 is exact for F1 and
+/// F2 with the Euclidean metric and one point per cell, higher features, other metrics and
+/// `points_per_cell` may need 2 to avoid cell artifacts.
+///
+/// `jitter` scales how far points stray from the cell centre, from a regular grid at 0 to anywhere
+/// in the cell at 1. With `points_per_cell` set, the number of points in each cell is drawn from a
+/// Poisson distribution with that mean instead of being one.
 #[derive(Debug, Builder)]
 pub struct WorleySampler {
     pub frequency: f32,
     #[builder(default = 0)]
     pub seed: u32,
+    /// Lattice cells the noise repeats after at `frequency`, the frequency itself if unset.
+    pub period: Option<u32>,
     #[builder(default = Smoothing::None)]
     pub smoothing: Smoothing,
     #[builder(default = WorleyMode::OneMinusF1)]
     pub mode: WorleyMode,
+    #[builder(default = WorleyMetric::Euclidean)]
+    pub metric: WorleyMetric,
+    #[builder(default = 1)]
+    pub radius: u32,
+    #[builder(default = 1.0)]
+    pub jitter: f32,
+    pub points_per_cell: Option<f32>,
+}
+impl WorleySampler {
+    fn finish(&self, features: Features) -> f32 {
+        let [f1, f2, f3, f4] = features.distances;
+
+        let value = match &self.mode {
+            WorleyMode::F1 => f1,
+            WorleyMode::F2 => f2,
+            WorleyMode::F3 => f3,
+            WorleyMode::F4 => f4,
+            WorleyMode::OneMinusF1 => 1.0 - f1,
+            WorleyMode::OneMinusF2 => 1.0 - f2,
+            WorleyMode::F2MinusF1 => f2 - f1,
+            WorleyMode::F1MinusF2 => f1 - f2,
+            WorleyMode::CellValue => features.cell_value,
+        };
+
+        self.smoothing.smooth(value.clamp(0.0, 1.0))
+    }
 }
 impl NoiseSampler<f32> for WorleySampler {
-    fn sample_2d(&mut self, mut uv: glam::Vec2) -> f32 {
-        uv *= glam::Vec2::splat(self.frequency);
+    fn sample_2d_with(&self, mut uv: glam::Vec2, params: SampleParams) -> f32 {
+        uv *= glam::Vec2::splat(params.frequency);
 
         let p = uv.floor().as_ivec2();
-        let f = uv.fract();
+        let f = uv.fract_gl();
 
-        let mut closest_dist: f32 = 1.0;
-        let mut second_closest_dist: f32 = 1.0;
+        let radius = self.radius as i32;
+        let mut features = Features::new();
 
-        for x in -1..=1 {
-            for y in -1..=1 {
+        for x in -radius..=radius {
+            for y in -radius..=radius {
                 let offset = glam::IVec2::new(x, y);
-                let cell = (p + offset).rem_euclid(glam::IVec2::splat(self.frequency as i32));
-
-                let hash3 = pcg_33(glam::UVec3::new(cell.x.cast_unsigned(), cell.y.cast_unsigned(), self.seed));
-                let point = (hash3.xy().as_vec2() / (u32::MAX as f32)) + offset.as_vec2();
+                let cell = (p + offset).rem_euclid(glam::IVec2::splat(params.period(self.period, self.frequency)));
 
-                let dist = f.distance(point);
-                if dist < closest_dist {
-                    second_closest_dist = closest_dist;
-                    closest_dist = dist;
-                } else if dist < second_closest_dist {
-                    second_closest_dist = dist;
+                for (point, hash) in cell_points_2d(cell.as_uvec2(), params.seed, self.jitter, self.points_per_cell) {
+                    let dist = self.metric.distance((point + offset.as_vec2() - f).to_array());
+                    features.insert(dist, hash as f32 / u32::MAX as f32);
                 }
             }
         }
 
-        let value = match &self.mode {
-            WorleyMode::F1 => closest_dist,
-            WorleyMode::F2 => second_closest_dist,
-            WorleyMode::OneMinusF1 => 1.0 - closest_dist,
-            WorleyMode::OneMinusF2 => 1.0 - second_closest_dist,
-            WorleyMode::F2MinusF1 => second_closest_dist - closest_dist,
-            WorleyMode::F1MinusF2 => closest_dist - second_closest_dist,
-        };
-
-        self.smoothing.smooth(value.clamp(0.0, 1.0))
+        self.finish(features)
     }
     
-    fn sample_3d(&mut self, mut uvw: glam::Vec3) -> f32 {
-        uvw *= glam::Vec3::splat(self.frequency);
+    fn sample_3d_with(&self, mut uvw: glam::Vec3, params: SampleParams) -> f32 {
+        uvw *= glam::Vec3::splat(params.frequency);
 
         let p = uvw.floor().as_ivec3();
-        let f = uvw.fract();
+        let f = uvw.fract_gl();
 
-        let mut closest_dist: f32 = 1.0;
-        let mut second_closest_dist: f32 = 1.0;
+        let radius = self.radius as i32;
+        let mut features = Features::new();
 
-        for x in -1..=1 {
-            for y in -1..=1 {
-                for z in -1..=1 {
+        for x in -radius..=radius {
+            for y in -radius..=radius {
+                for z in -radius..=radius {
                     let offset = glam::IVec3::new(x, y, z);
-                    let cell = (p + offset).rem_euclid(glam::IVec3::splat(self.frequency as i32));
-
-                    let hash4 = pcg_44(glam::UVec4::new(cell.x.cast_unsigned(), cell.y.cast_unsigned(), cell.z.cast_unsigned(), self.seed));
-                    let point = (hash4.xyz().as_vec3() / (u32::MAX as f32)) + offset.as_vec3();
+                    let cell = (p + offset).rem_euclid(glam::IVec3::splat(params.period(self.period, self.frequency)));
 
-                    let dist = f.distance(point);
-                    if dist < closest_dist {
-                        second_closest_dist = closest_dist;
-                        closest_dist = dist;
-                    } else if dist < second_closest_dist {
-                        second_closest_dist = dist;
+                    for (point, hash) in cell_points_3d(cell.as_uvec3(), params.seed, self.jitter, self.points_per_cell) {
+                        let dist = self.metric.distance((point + offset.as_vec3() - f).to_array());
+                        features.insert(dist, hash as f32 / u32::MAX as f32);
                     }
                 }
             }
         }
 
-        let value = match &self.mode {
-            WorleyMode::F1 => closest_dist,
-            WorleyMode::F2 => second_closest_dist,
-            WorleyMode::OneMinusF1 => 1.0 - closest_dist,
-            WorleyMode::OneMinusF2 => 1.0 - second_closest_dist,
-            WorleyMode::F2MinusF1 => second_closest_dist - closest_dist,
-            WorleyMode::F1MinusF2 => closest_dist - second_closest_dist,
-        };
+        self.finish(features)
+    }
 
-        self.smoothing.smooth(value.clamp(0.0, 1.0))
+    fn sample_4d_with(&self, mut uvwt: glam::Vec4, params: SampleParams) -> f32 {
+        uvwt *= glam::Vec4::splat(params.frequency);
+
+        let p = uvwt.floor().as_ivec4();
+        let f = uvwt - p.as_vec4();
+
+        let radius = self.radius as i32;
+        let mut features = Features::new();
+
+        for x in -radius..=radius {
+            for y in -radius..=radius {
+                for z in -radius..=radius {
+                    for w in -radius..=radius {
+                        let offset = glam::IVec4::new(x, y, z, w);
+                        let cell = (p + offset).rem_euclid(glam::IVec4::splat(params.period(self.period, self.frequency)));
+
+                        for (point, hash) in cell_points_4d(cell.as_uvec4(), params.seed, self.jitter, self.points_per_cell) {
+                            let dist = self.metric.distance((point + offset.as_vec4() - f).to_array());
+                            features.insert(dist, hash as f32 / u32::MAX as f32);
+                        }
+                    }
+                }
+            }
+        }
+
+        self.finish(features)
     }
 }
 impl NoiseSamplerState for WorleySampler {
@@ -116,6 +212,10 @@ impl NoiseSamplerState for WorleySampler {
         self.seed
     }
 
+    fn get_period(&self) -> Option<u32> {
+        self.period
+    }
+
     fn set_frequency(&mut self, new_frequency: f32) {
         self.frequency = new_frequency;
     }
@@ -124,3 +224,113 @@ impl NoiseSamplerState for WorleySampler {
         self.seed = new_seed;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEATURES: [WorleyMode; 4] = [WorleyMode::F1, WorleyMode::F2, WorleyMode::F3, WorleyMode::F4];
+
+    fn sampler(mode: WorleyMode) -> WorleySampler {
+        WorleySampler::builder().frequency(4.0).seed(7).radius(2).mode(mode).build()
+    }
+
+    #[test]
+    fn metrics() {
+        let delta = [3.0, -4.0];
+        let cases = [
+            (WorleyMetric::Euclidean, 5.0),
+            (WorleyMetric::EuclideanSquared, 25.0),
+            (WorleyMetric::Manhattan, 7.0),
+            (WorleyMetric::Chebyshev, 4.0),
+            (WorleyMetric::Minkowski(1.0), 7.0),
+            (WorleyMetric::Minkowski(2.0), 5.0),
+        ];
+        for (metric, expected) in cases {
+            let distance = metric.distance(delta);
+            assert!((distance - expected).abs() < 1e-4, "{metric:?} gave {distance}, expected {expected}");
+        }
+    }
+
+    #[test]
+    fn features_are_ordered() {
+        let samplers = FEATURES.map(sampler);
+        for index in 0..256 {
+            let uvw = glam::uvec3(index, index * 37 % 256, index * 91 % 256).as_vec3() / 256.0;
+
+            let features_2d = samplers.each_ref().map(|sampler| sampler.sample_2d(uvw.truncate()));
+            let features_3d = samplers.each_ref().map(|sampler| sampler.sample_3d(uvw));
+            let features_4d = samplers.each_ref().map(|sampler| sampler.sample_4d(uvw.extend(0.3)));
+            for features in [features_2d, features_3d, features_4d] {
+                assert!(features.is_sorted(), "features {features:?} out of order at {uvw}");
+            }
+        }
+    }
+
+    #[test]
+    fn zero_jitter_gives_a_regular_grid() {
+        let sampler = WorleySampler::builder().frequency(4.0).seed(3).jitter(0.0).mode(WorleyMode::F1).build();
+
+        for x in 0..4 {
+            for y in 0..4 {
+                let corner = glam::vec2(x as f32, y as f32) / 4.0;
+                let centre = sampler.sample_2d(corner + 0.125);
+                assert!(centre < 1e-4, "F1 of {centre} at the centre of cell {x}, {y}");
+
+                let distance = sampler.sample_2d(corner);
+                assert!((distance - 0.5f32.sqrt()).abs() < 1e-4, "F1 of {distance} at the corner of cell {x}, {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn cell_value_is_constant_across_a_regular_cell() {
+        let sampler = WorleySampler::builder()
+            .frequency(4.0)
+            .seed(3)
+            .jitter(0.0)
+            .mode(WorleyMode::CellValue)
+            .build();
+
+        let mut values = Vec::new();
+        for x in 0..4 {
+            for y in 0..4 {
+                let centre = (glam::vec2(x as f32, y as f32) + 0.5) / 4.0;
+                let value = sampler.sample_2d(centre);
+                for offset in [glam::vec2(0.1, 0.05), glam::vec2(-0.08, 0.1), glam::vec2(0.04, -0.1)] {
+                    let moved = sampler.sample_2d(centre + offset / 4.0);
+                    assert_eq!(moved, value, "cell value changed within cell {x}, {y}");
+                }
+                values.push(value);
+            }
+        }
+        assert!(values.iter().any(|value| *value != values[0]), "every cell has the value {}", values[0]);
+    }
+
+    #[test]
+    fn tiles_with_a_wider_radius_and_more_points() {
+        let step = 1e-4;
+        for (frequency, period) in [(3.0, None), (6.0, Some(3))] {
+            for mode in FEATURES.into_iter().chain([WorleyMode::CellValue]) {
+                let sampler = WorleySampler::builder()
+                    .frequency(frequency)
+                    .maybe_period(period)
+                    .seed(5)
+                    .radius(2)
+                    .points_per_cell(2.5)
+                    .metric(WorleyMetric::Manhattan)
+                    .mode(mode)
+                    .build();
+
+                for t in [0.1, 0.45, 0.7] {
+                    for axis in 0..3 {
+                        let inside = glam::Vec3::splat(t) + glam::Vec3::AXES[axis] * (1.0 - t - step);
+                        let outside = inside + glam::Vec3::AXES[axis] * 2.0 * step;
+                        let jump = (sampler.sample_3d(inside) - sampler.sample_3d(outside)).abs();
+                        assert!(jump < 1e-2, "{mode:?} jumps by {jump} along axis {axis} at frequency {frequency}");
+                    }
+                }
+            }
+        }
+    }
+}
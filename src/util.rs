@@ -21,6 +21,29 @@ pub fn mix_vec3(x: glam::Vec3, y: glam::Vec3, alpha: f32) -> glam::Vec3 {
     x * (1.0f32 - alpha) + y * alpha
 }
 
+/// Offset of corner `index` of a 4D lattice cell, whose bits are the x, y, z and w offsets.
+pub fn corner_4d(index: usize) -> glam::IVec4 {
+    glam::IVec4::from_array(std::array::from_fn(|axis| ((index >> axis) & 1) as i32))
+}
+
+/// Interpolates the 16 corners of a 4D lattice cell, indexed as in [`corner_4d`], by `alpha` along
+/// each axis in turn.
+pub fn mix_corners_4d<T>(mut corners: [T; 16], alpha: glam::Vec4) -> T
+where
+    T: Copy + std::ops::Add<Output = T> + std::ops::Mul<f32, Output = T>,
+{
+    let mut count = corners.len();
+    for axis in 0..4 {
+        count /= 2;
+        for index in 0..count {
+            corners[index] =
+                corners[index * 2] * (1.0 - alpha[axis]) + corners[index * 2 + 1] * alpha[axis];
+        }
+    }
+
+    corners[0]
+}
+
 pub fn remap(value: f32, old_min: f32, old_max: f32, new_min: f32, new_max: f32) -> f32 {
     new_min + (((value - old_min) / (old_max - old_min)) * (new_max - new_min))
 }
@@ -82,6 +105,11 @@ impl Normalized {
         self.max.load(Ordering::Relaxed)
     }
 }
+impl Default for Normalized {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 pub trait MappingFn<T> {
     fn map(self, min: T, max: T) -> Self;
@@ -167,6 +195,18 @@ impl SmoothingFn for glam::Vec3 {
     }
 }
 
+impl SmoothingFn for glam::Vec4 {
+    fn cubic_smooth(mut self) -> Self {
+        self = self.clamp(glam::Vec4::ZERO, glam::Vec4::ONE);
+        self * self * (3.0 - 2.0 * self)
+    }
+
+    fn quintic_smooth(mut self) -> Self {
+        self = self.clamp(glam::Vec4::ZERO, glam::Vec4::ONE);
+        self * self * self * (self * (self * 6.0 - 15.0) + 10.0)
+    }
+}
+
 pub trait CoordWrapping {
     fn wrap_coord(self, size: Self) -> Self;
 }